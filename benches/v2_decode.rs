@@ -3,7 +3,10 @@ use criterion::{
 };
 use lexical::to_string;
 use rand::random;
-use rresp::{decode, v2::V2};
+use rresp::{
+    decode,
+    v2::{DecodeArena, Decoder, V2},
+};
 
 fn build_bulk(len: usize) -> Vec<u8> {
     let mut buf = Vec::new();
@@ -152,5 +155,66 @@ fn v2_decode(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, v2_decode);
+fn v2_frame_len(c: &mut Criterion) {
+    let bulk_params = DecodeBulkParams::new();
+    let array_params = DecodeArrayParams::new();
+    let mut group = c.benchmark_group("v2_frame_len");
+
+    for (bulk, len) in bulk_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("frame_len_bulk", len), &bulk, |b, i| {
+            b.iter(|| V2::frame_len(black_box(i)).unwrap().unwrap());
+        });
+    }
+
+    for (array, len) in array_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("frame_len_array", len), &array, |b, i| {
+            b.iter(|| V2::frame_len(black_box(i)).unwrap().unwrap());
+        });
+        group.bench_with_input(BenchmarkId::new("decode_array", len), &array, |b, i| {
+            b.iter(|| decode::<V2>(black_box(i)).unwrap().unwrap());
+        });
+    }
+}
+
+fn v2_decode_arena(c: &mut Criterion) {
+    let array_params = DecodeArrayParams::new();
+    let mut group = c.benchmark_group("v2_decode_arena");
+
+    for (array, len) in array_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("arena_array", len), &array, |b, i| {
+            let mut arena = DecodeArena::new();
+            b.iter(|| {
+                arena.decode(black_box(i)).unwrap().unwrap();
+            });
+        });
+    }
+}
+
+fn v2_decoder(c: &mut Criterion) {
+    let command = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+    let mut group = c.benchmark_group("v2_decoder");
+
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("decode_command", |b| {
+        b.iter(|| decode::<V2>(black_box(command)).unwrap().unwrap());
+    });
+    group.bench_function("decoder_command", |b| {
+        let mut decoder = Decoder::new();
+        b.iter(|| {
+            let (frame, _) = decoder.decode(black_box(command)).unwrap().unwrap();
+            decoder.recycle(frame);
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    v2_decode,
+    v2_frame_len,
+    v2_decode_arena,
+    v2_decoder
+);
 criterion_main!(benches);
@@ -1,3 +1,4 @@
+use bytes::BytesMut;
 use criterion::{
     black_box, criterion_group, criterion_main, Bencher, BenchmarkId, Criterion, Throughput,
 };
@@ -43,5 +44,39 @@ fn v2_encode(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, v2_encode);
+fn v2_encode_to_buf(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v2_encode_to_buf");
+
+    let frame = Frame::Array(
+        (0..100)
+            .map(|i| {
+                if i % 2 == 0 {
+                    Frame::BulkString(b"hello world")
+                } else {
+                    Frame::Integer(i * 1024)
+                }
+            })
+            .collect(),
+    );
+    group.throughput(Throughput::Bytes(frame.encode_len() as u64));
+    group.bench_function("encode_with_writer", |b| {
+        let mut buf = Vec::with_capacity(frame.encode_len());
+        b.iter(|| {
+            buf.clear();
+            black_box(&frame).encode_with_writer(&mut buf).unwrap();
+        });
+    });
+    group.bench_function("encode_to_buf", |b| {
+        let mut buf = BytesMut::with_capacity(frame.encode_len());
+        b.iter(|| {
+            buf.clear();
+            black_box(&frame).encode_to_buf(&mut buf);
+        });
+    });
+    group.bench_function("encode", |b| {
+        b.iter(|| black_box(&frame).encode());
+    });
+}
+
+criterion_group!(benches, v2_encode, v2_encode_to_buf);
 criterion_main!(benches);
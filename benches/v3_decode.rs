@@ -2,7 +2,10 @@ use criterion::{
     black_box, criterion_group, criterion_main, Bencher, BenchmarkId, Criterion, Throughput,
 };
 use lexical::to_string;
-use rresp::{decode, v3::V3};
+use rresp::{
+    decode,
+    v3::{DecodeArena, Tape, V3},
+};
 
 fn build_bulk(len: usize) -> Vec<u8> {
     let mut buf = Vec::new();
@@ -168,5 +171,88 @@ fn v3_decode(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, v3_decode);
+fn v3_decode_tape(c: &mut Criterion) {
+    let array_params = DecodeArrayParams::new();
+
+    let mut group = c.benchmark_group("v3_decode_tape");
+
+    for (array, len) in array_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("tape_array", len), &array, |b, i| {
+            let mut tape = Tape::default();
+            b.iter(|| {
+                tape.parse_into(black_box(i)).unwrap().unwrap();
+                let root = tape.root().unwrap();
+                black_box(root.get(0).unwrap().as_bytes());
+            });
+        });
+    }
+}
+
+fn v3_frame_len(c: &mut Criterion) {
+    let array_params = DecodeArrayParams::new();
+    let attribute_params = DecodeAttributeParams::new();
+
+    let mut group = c.benchmark_group("v3_frame_len");
+
+    for (array, len) in array_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("frame_len_array", len), &array, |b, i| {
+            b.iter(|| V3::frame_len(black_box(i)).unwrap().unwrap());
+        });
+        group.bench_with_input(BenchmarkId::new("decode_array", len), &array, |b, i| {
+            b.iter(|| decode::<V3>(black_box(i)).unwrap().unwrap());
+        });
+    }
+
+    for (attribute, len) in attribute_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(
+            BenchmarkId::new("frame_len_attribute", len),
+            &attribute,
+            |b, i| {
+                b.iter(|| V3::frame_len(black_box(i)).unwrap().unwrap());
+            },
+        );
+    }
+}
+
+fn v3_decode_arena(c: &mut Criterion) {
+    let array_params = DecodeArrayParams::new();
+    let array_tree_params = DecodeArrayTreeParams::new();
+
+    let mut group = c.benchmark_group("v3_decode_arena");
+
+    for (array, len) in array_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(BenchmarkId::new("arena_array", len), &array, |b, i| {
+            let mut arena = DecodeArena::new();
+            b.iter(|| {
+                arena.decode(black_box(i)).unwrap().unwrap();
+            });
+        });
+    }
+
+    for (array_tree, len) in array_tree_params.0 {
+        group.throughput(Throughput::Elements(len as u64));
+        group.bench_with_input(
+            BenchmarkId::new("arena_array_tree", len),
+            &array_tree,
+            |b, i| {
+                let mut arena = DecodeArena::new();
+                b.iter(|| {
+                    arena.decode(black_box(i)).unwrap().unwrap();
+                });
+            },
+        );
+    }
+}
+
+criterion_group!(
+    benches,
+    v3_decode,
+    v3_decode_tape,
+    v3_frame_len,
+    v3_decode_arena
+);
 criterion_main!(benches);
@@ -1,3 +1,4 @@
+use bytes::BytesMut;
 use criterion::{
     black_box, criterion_group, criterion_main, Bencher, BenchmarkId, Criterion, Throughput,
 };
@@ -73,5 +74,45 @@ fn v3_encode(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, v3_encode);
+fn v3_encode_to_buf(c: &mut Criterion) {
+    let mut group = c.benchmark_group("v3_encode_to_buf");
+
+    let frame = Frame::Array {
+        data: (0..100)
+            .map(|i| match i % 3 {
+                0 => Frame::BulkString {
+                    data: b"hello world",
+                    attributes: None,
+                },
+                1 => Frame::Integer {
+                    data: i * 1024,
+                    attributes: None,
+                },
+                _ => Frame::Double {
+                    data: i as f64 / 3.0,
+                    raw: None,
+                    attributes: None,
+                },
+            })
+            .collect(),
+        attributes: None,
+    };
+    group.throughput(Throughput::Bytes(frame.encode_len() as u64));
+    group.bench_function("encode_with_writer", |b| {
+        let mut buf = Vec::with_capacity(frame.encode_len());
+        b.iter(|| {
+            buf.clear();
+            black_box(&frame).encode_with_writer(&mut buf).unwrap();
+        });
+    });
+    group.bench_function("encode_to_buf", |b| {
+        let mut buf = BytesMut::with_capacity(frame.encode_len());
+        b.iter(|| {
+            buf.clear();
+            black_box(&frame).encode_to_buf(&mut buf);
+        });
+    });
+}
+
+criterion_group!(benches, v3_encode, v3_encode_to_buf);
 criterion_main!(benches);
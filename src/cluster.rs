@@ -0,0 +1,163 @@
+use crate::{command::builtin_table, v2::Frame};
+
+pub const SLOT_COUNT: u16 = 16384;
+
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+/// CRC16-CCITT (XMODEM), the checksum Redis Cluster uses to map keys to slots.
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0u16; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = (index as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, byte| {
+        (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize]
+    })
+}
+
+/// Only the part between the first `{` and the following `}` is hashed when
+/// it is non-empty, so related keys can be forced into the same slot.
+pub fn key_hash_slot(key: &[u8]) -> u16 {
+    let hashed = key
+        .iter()
+        .position(|byte| *byte == b'{')
+        .and_then(|open| {
+            let tag = &key[open + 1..];
+            let close = tag.iter().position(|byte| *byte == b'}')?;
+            Some(&tag[..close])
+        })
+        .filter(|tag| !tag.is_empty())
+        .unwrap_or(key);
+
+    crc16(hashed) & (SLOT_COUNT - 1)
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum KeySlot {
+    NoKeys,
+    Slot(u16),
+    CrossSlot,
+}
+
+/// Extracts the key arguments of a decoded request using the built-in
+/// command table. Returns `None` when the frame is not a command or the
+/// command is not known.
+pub fn command_keys<'a>(command: &Frame<'a>) -> Option<Vec<&'a [u8]>> {
+    builtin_table().command_keys(command)
+}
+
+/// Computes the slot all keys map to, reporting `CrossSlot` when they span
+/// several slots.
+pub fn keys_slot(keys: &[&[u8]]) -> KeySlot {
+    let mut slots = keys.iter().map(|key| key_hash_slot(key));
+
+    let slot = match slots.next() {
+        Some(slot) => slot,
+        None => return KeySlot::NoKeys,
+    };
+    if slots.all(|other| other == slot) {
+        KeySlot::Slot(slot)
+    } else {
+        KeySlot::CrossSlot
+    }
+}
+
+/// Computes the slot all keys of a decoded request map to, reporting
+/// `CrossSlot` when they span several slots.
+pub fn command_slot(command: &Frame<'_>) -> Option<KeySlot> {
+    command_keys(command).map(|keys| keys_slot(&keys))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use minivec::{mini_vec, MiniVec};
+
+    fn command<'a>(args: &[&'a [u8]]) -> Frame<'a> {
+        Frame::Array(
+            args.iter()
+                .map(|arg| Frame::BulkString(arg))
+                .collect::<MiniVec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_key_hash_slot() {
+        assert_eq!(key_hash_slot(b"123456789"), 0x31c3 & (SLOT_COUNT - 1));
+        assert_eq!(key_hash_slot(b"foo"), 12182);
+        assert_eq!(key_hash_slot(b"{foo}.bar"), 12182);
+        assert_eq!(key_hash_slot(b"bar{foo}"), 12182);
+        assert_eq!(
+            key_hash_slot(b"foo{}{bar}"),
+            crc16(b"foo{}{bar}") & (SLOT_COUNT - 1)
+        );
+        assert_eq!(key_hash_slot(b"foo{{bar}}zap"), key_hash_slot(b"{bar"));
+        assert_eq!(
+            key_hash_slot(b"foo{bar"),
+            crc16(b"foo{bar") & (SLOT_COUNT - 1)
+        );
+    }
+
+    #[test]
+    fn test_command_keys() {
+        let frame = command(&[b"get", b"foo"]);
+        assert_eq!(command_keys(&frame), Some(vec![&b"foo"[..]]));
+
+        let frame = command(&[b"MSET", b"a", b"1", b"b", b"2"]);
+        assert_eq!(command_keys(&frame), Some(vec![&b"a"[..], b"b"]));
+
+        let frame = command(&[b"BLPOP", b"a", b"b", b"0"]);
+        assert_eq!(command_keys(&frame), Some(vec![&b"a"[..], b"b"]));
+
+        let frame = command(&[b"EVAL", b"return 1", b"2", b"a", b"b", b"arg"]);
+        assert_eq!(command_keys(&frame), Some(vec![&b"a"[..], b"b"]));
+
+        let frame = command(&[
+            b"XREAD", b"COUNT", b"2", b"STREAMS", b"s1", b"s2", b"0", b"0",
+        ]);
+        assert_eq!(command_keys(&frame), Some(vec![&b"s1"[..], b"s2"]));
+
+        let frame = command(&[b"EVAL", b"return 1", b"3", b"a"]);
+        assert_eq!(command_keys(&frame), None);
+
+        let frame = command(&[b"EVAL", b"return 1", b"9223372036854775807", b"a"]);
+        assert_eq!(command_keys(&frame), None);
+
+        let frame = command(&[b"NOSUCHCOMMAND", b"a"]);
+        assert_eq!(command_keys(&frame), None);
+
+        let frame = Frame::Array(mini_vec![Frame::BulkString(b"GET"), Frame::Integer(1)]);
+        assert_eq!(command_keys(&frame), None);
+    }
+
+    #[test]
+    fn test_command_slot() {
+        let frame = command(&[b"MGET", b"{user}.name", b"{user}.age"]);
+        assert_eq!(
+            command_slot(&frame),
+            Some(KeySlot::Slot(key_hash_slot(b"user")))
+        );
+
+        let frame = command(&[b"MGET", b"foo", b"bar"]);
+        assert_eq!(command_slot(&frame), Some(KeySlot::CrossSlot));
+
+        let frame = command(&[b"EVAL", b"return 1", b"0"]);
+        assert_eq!(command_slot(&frame), Some(KeySlot::NoKeys));
+    }
+}
@@ -0,0 +1,831 @@
+use std::{borrow::Cow, collections::HashMap, ops::BitOr, sync::OnceLock};
+
+use crate::{v2::Frame as V2Frame, v3::Frame as V3Frame, Error};
+
+/// The server version the built-in command table was taken from.
+pub const BUILTIN_VERSION: &str = "7.2.0";
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct CommandFlags(u32);
+
+impl CommandFlags {
+    pub const WRITE: Self = Self(1);
+    pub const READONLY: Self = Self(1 << 1);
+    pub const DENYOOM: Self = Self(1 << 2);
+    pub const ADMIN: Self = Self(1 << 3);
+    pub const PUBSUB: Self = Self(1 << 4);
+    pub const NOSCRIPT: Self = Self(1 << 5);
+    pub const BLOCKING: Self = Self(1 << 6);
+    pub const LOADING: Self = Self(1 << 7);
+    pub const STALE: Self = Self(1 << 8);
+    pub const FAST: Self = Self(1 << 9);
+    pub const MOVABLE_KEYS: Self = Self(1 << 10);
+    pub const NO_AUTH: Self = Self(1 << 11);
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Maps a flag name as reported by `COMMAND INFO`. Flags this table does
+    /// not track map to `None`.
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        let flag = match name {
+            b"write" => Self::WRITE,
+            b"readonly" => Self::READONLY,
+            b"denyoom" => Self::DENYOOM,
+            b"admin" => Self::ADMIN,
+            b"pubsub" => Self::PUBSUB,
+            b"noscript" => Self::NOSCRIPT,
+            b"blocking" => Self::BLOCKING,
+            b"loading" => Self::LOADING,
+            b"stale" => Self::STALE,
+            b"fast" => Self::FAST,
+            b"movablekeys" => Self::MOVABLE_KEYS,
+            b"no_auth" => Self::NO_AUTH,
+            _ => return None,
+        };
+        Some(flag)
+    }
+}
+
+impl BitOr for CommandFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
+
+/// Where the search for keys starts, mirroring the `begin_search` part of a
+/// Redis key specification.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BeginSearch {
+    Index(usize),
+    /// Keys start after `keyword`, searched from `start_from` (negative
+    /// values search backwards from the end).
+    Keyword {
+        keyword: Cow<'static, str>,
+        start_from: isize,
+    },
+}
+
+/// How keys are found once the search has started, mirroring the
+/// `find_keys` part of a Redis key specification.
+#[derive(Debug, PartialEq, Clone)]
+pub enum FindKeys {
+    /// `last_key` is relative to the first key, negative values count from the
+    /// end. A non-zero `limit` only takes `1 / limit` of the remaining
+    /// arguments.
+    Range {
+        last_key: isize,
+        step: usize,
+        limit: usize,
+    },
+    /// The argument at `keynum_index` holds the number of keys, the first of
+    /// which is at `first_key`; both are relative to the search start.
+    Keynum {
+        keynum_index: usize,
+        first_key: usize,
+        step: usize,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct KeySpec {
+    pub begin_search: BeginSearch,
+    pub find_keys: FindKeys,
+}
+
+impl KeySpec {
+    pub const fn range(index: usize, last_key: isize, step: usize) -> Self {
+        Self {
+            begin_search: BeginSearch::Index(index),
+            find_keys: FindKeys::Range {
+                last_key,
+                step,
+                limit: 0,
+            },
+        }
+    }
+
+    pub const fn keynum(index: usize) -> Self {
+        Self {
+            begin_search: BeginSearch::Index(index),
+            find_keys: FindKeys::Keynum {
+                keynum_index: 0,
+                first_key: 1,
+                step: 1,
+            },
+        }
+    }
+
+    /// Returns `None` when the arguments do not hold the keys they declare,
+    /// such as a key count running past the last argument.
+    fn push_keys<'a>(&self, args: &[&'a [u8]], keys: &mut Vec<&'a [u8]>) -> Option<()> {
+        let argc = args.len() as isize;
+        let first = match &self.begin_search {
+            BeginSearch::Index(index) => isize::try_from(*index).ok()?,
+            BeginSearch::Keyword {
+                keyword,
+                start_from,
+            } => {
+                let start = if *start_from > 0 {
+                    *start_from
+                } else {
+                    argc + start_from
+                };
+                let found = if *start_from > 0 {
+                    (start.max(1)..argc)
+                        .find(|i| args[*i as usize].eq_ignore_ascii_case(keyword.as_bytes()))
+                } else {
+                    (1..=start.min(argc - 1))
+                        .rev()
+                        .find(|i| args[*i as usize].eq_ignore_ascii_case(keyword.as_bytes()))
+                };
+                match found {
+                    Some(position) => position + 1,
+                    None => return Some(()),
+                }
+            }
+        };
+
+        let (first, last, step) = match &self.find_keys {
+            FindKeys::Range {
+                last_key,
+                step,
+                limit,
+            } => {
+                let last = if *last_key >= 0 {
+                    first.checked_add(*last_key)?
+                } else if *limit == 0 {
+                    argc + last_key
+                } else {
+                    let limit = isize::try_from(*limit).unwrap_or(isize::MAX);
+                    (first + (argc - first) / limit).checked_add(*last_key)?
+                };
+                (first, last, *step)
+            }
+            FindKeys::Keynum {
+                keynum_index,
+                first_key,
+                step,
+            } => {
+                let index = usize::try_from(first).ok()?.checked_add(*keynum_index)?;
+                let count = lexical::parse::<usize, _>(args.get(index)?).ok()?;
+                let first = first.checked_add(isize::try_from(*first_key).ok()?)?;
+                let end = first.checked_add(isize::try_from(count).ok()?)?;
+                if end > argc {
+                    return None;
+                }
+                (first, end - 1, *step)
+            }
+        };
+
+        let step = isize::try_from(step.max(1)).unwrap_or(isize::MAX);
+        let mut index = first;
+        while index <= last && index < argc {
+            keys.push(args[index as usize]);
+            let Some(next) = index.checked_add(step) else {
+                break;
+            };
+            index = next;
+        }
+        Some(())
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct CommandSpec {
+    pub name: Cow<'static, str>,
+    /// Positive values are an exact argument count including the command
+    /// name, negative values a minimum.
+    pub arity: i64,
+    pub flags: CommandFlags,
+    pub key_specs: Cow<'static, [KeySpec]>,
+}
+
+impl CommandSpec {
+    pub fn check_arity(&self, argc: usize) -> bool {
+        let argc = argc as i64;
+        if self.arity >= 0 {
+            argc == self.arity
+        } else {
+            argc >= -self.arity
+        }
+    }
+
+    /// Extracts the key arguments, `args[0]` being the command name.
+    /// Returns `None` when the arguments hold fewer keys than they declare.
+    pub fn keys<'a>(&self, args: &[&'a [u8]]) -> Option<Vec<&'a [u8]>> {
+        let mut keys = Vec::new();
+        for spec in self.key_specs.iter() {
+            spec.push_keys(args, &mut keys)?;
+        }
+        Some(keys)
+    }
+}
+
+const fn command(
+    name: &'static str,
+    arity: i64,
+    flags: CommandFlags,
+    key_specs: &'static [KeySpec],
+) -> CommandSpec {
+    CommandSpec {
+        name: Cow::Borrowed(name),
+        arity,
+        flags,
+        key_specs: Cow::Borrowed(key_specs),
+    }
+}
+
+const NO_KEYS: &[KeySpec] = &[];
+const KEY: &[KeySpec] = &[KeySpec::range(1, 0, 1)];
+const ALL_KEYS: &[KeySpec] = &[KeySpec::range(1, -1, 1)];
+const KEY_VALUES: &[KeySpec] = &[KeySpec::range(1, -1, 2)];
+const TWO_KEYS: &[KeySpec] = &[KeySpec::range(1, 0, 1), KeySpec::range(2, 0, 1)];
+const STORE_KEYS: &[KeySpec] = &[KeySpec::range(1, 0, 1), KeySpec::range(2, -1, 1)];
+const STORE_KEYNUM: &[KeySpec] = &[KeySpec::range(1, 0, 1), KeySpec::keynum(2)];
+const BLOCKING_KEYS: &[KeySpec] = &[KeySpec::range(1, -2, 1)];
+const SCRIPT_KEYS: &[KeySpec] = &[KeySpec::keynum(2)];
+const fn streams(start_from: isize) -> KeySpec {
+    KeySpec {
+        begin_search: BeginSearch::Keyword {
+            keyword: Cow::Borrowed("STREAMS"),
+            start_from,
+        },
+        find_keys: FindKeys::Range {
+            last_key: -1,
+            step: 1,
+            limit: 2,
+        },
+    }
+}
+const XREAD_KEYS: &[KeySpec] = &[streams(1)];
+const XREADGROUP_KEYS: &[KeySpec] = &[streams(4)];
+
+const R: CommandFlags = CommandFlags::READONLY;
+const W: CommandFlags = CommandFlags::WRITE;
+const RF: CommandFlags = CommandFlags::READONLY.union(CommandFlags::FAST);
+const WF: CommandFlags = CommandFlags::WRITE.union(CommandFlags::FAST);
+const WD: CommandFlags = CommandFlags::WRITE.union(CommandFlags::DENYOOM);
+const WDF: CommandFlags = WD.union(CommandFlags::FAST);
+const WB: CommandFlags = CommandFlags::WRITE.union(CommandFlags::BLOCKING);
+const SCRIPT: CommandFlags = CommandFlags::NOSCRIPT.union(CommandFlags::MOVABLE_KEYS);
+const SCRIPT_RO: CommandFlags = SCRIPT.union(CommandFlags::READONLY);
+const PUBSUB: CommandFlags = CommandFlags::PUBSUB.union(CommandFlags::NOSCRIPT);
+const CONNECTION: CommandFlags = CommandFlags::FAST.union(CommandFlags::LOADING);
+
+pub static BUILTIN_COMMANDS: &[CommandSpec] = &[
+    command("get", 2, RF, KEY),
+    command("set", -3, WD, KEY),
+    command("setnx", 3, WDF, KEY),
+    command("setex", 4, WD, KEY),
+    command("psetex", 4, WD, KEY),
+    command("getset", 3, WDF, KEY),
+    command("getdel", 2, WF, KEY),
+    command("getex", -2, WF, KEY),
+    command("append", 3, WDF, KEY),
+    command("strlen", 2, RF, KEY),
+    command("getrange", 4, R, KEY),
+    command("setrange", 4, WD, KEY),
+    command("incr", 2, WDF, KEY),
+    command("decr", 2, WDF, KEY),
+    command("incrby", 3, WDF, KEY),
+    command("decrby", 3, WDF, KEY),
+    command("incrbyfloat", 3, WDF, KEY),
+    command("mget", -2, RF, ALL_KEYS),
+    command("mset", -3, WD, KEY_VALUES),
+    command("msetnx", -3, WD, KEY_VALUES),
+    command("del", -2, W, ALL_KEYS),
+    command("unlink", -2, WF, ALL_KEYS),
+    command("exists", -2, RF, ALL_KEYS),
+    command("touch", -2, RF, ALL_KEYS),
+    command("expire", -3, WF, KEY),
+    command("pexpire", -3, WF, KEY),
+    command("expireat", -3, WF, KEY),
+    command("pexpireat", -3, WF, KEY),
+    command("ttl", 2, RF, KEY),
+    command("pttl", 2, RF, KEY),
+    command("persist", 2, WF, KEY),
+    command("type", 2, RF, KEY),
+    command("rename", 3, W, TWO_KEYS),
+    command("renamenx", 3, WF, TWO_KEYS),
+    command("copy", -3, WD, TWO_KEYS),
+    command("dump", 2, R, KEY),
+    command("restore", -4, WD, KEY),
+    command("getbit", 3, RF, KEY),
+    command("setbit", 4, WD, KEY),
+    command("bitcount", -2, R, KEY),
+    command("hget", 3, RF, KEY),
+    command("hset", -4, WDF, KEY),
+    command("hsetnx", 4, WDF, KEY),
+    command("hmset", -4, WDF, KEY),
+    command("hmget", -3, RF, KEY),
+    command("hdel", -3, WF, KEY),
+    command("hgetall", 2, R, KEY),
+    command("hkeys", 2, R, KEY),
+    command("hvals", 2, R, KEY),
+    command("hlen", 2, RF, KEY),
+    command("hexists", 3, RF, KEY),
+    command("hincrby", 4, WDF, KEY),
+    command("lpush", -3, WDF, KEY),
+    command("rpush", -3, WDF, KEY),
+    command("lpop", -2, WF, KEY),
+    command("rpop", -2, WF, KEY),
+    command("llen", 2, RF, KEY),
+    command("lrange", 4, R, KEY),
+    command("lindex", 3, R, KEY),
+    command("lset", 4, WD, KEY),
+    command("lrem", 4, W, KEY),
+    command("ltrim", 4, W, KEY),
+    command("rpoplpush", 3, WD, TWO_KEYS),
+    command("lmove", 5, WD, TWO_KEYS),
+    command("blpop", -3, WB, BLOCKING_KEYS),
+    command("brpop", -3, WB, BLOCKING_KEYS),
+    command("blmove", 6, WB.union(CommandFlags::DENYOOM), TWO_KEYS),
+    command("sadd", -3, WDF, KEY),
+    command("srem", -3, WF, KEY),
+    command("smembers", 2, R, KEY),
+    command("sismember", 3, RF, KEY),
+    command("scard", 2, RF, KEY),
+    command("spop", -2, WF, KEY),
+    command("srandmember", -2, R, KEY),
+    command("smove", 4, WF, TWO_KEYS),
+    command("sinter", -2, R, ALL_KEYS),
+    command("sunion", -2, R, ALL_KEYS),
+    command("sdiff", -2, R, ALL_KEYS),
+    command("sinterstore", -3, WD, STORE_KEYS),
+    command("sunionstore", -3, WD, STORE_KEYS),
+    command("sdiffstore", -3, WD, STORE_KEYS),
+    command("zadd", -4, WDF, KEY),
+    command("zrem", -3, WF, KEY),
+    command("zrange", -4, R, KEY),
+    command("zrevrange", -4, R, KEY),
+    command("zrangebyscore", -4, R, KEY),
+    command("zscore", 3, RF, KEY),
+    command("zcard", 2, RF, KEY),
+    command("zincrby", 4, WDF, KEY),
+    command("zrank", -3, RF, KEY),
+    command("zunionstore", -4, WD, STORE_KEYNUM),
+    command("zinterstore", -4, WD, STORE_KEYNUM),
+    command("pfadd", -2, WDF, KEY),
+    command("pfcount", -2, R, ALL_KEYS),
+    command("xadd", -5, WDF, KEY),
+    command("xlen", 2, RF, KEY),
+    command("xrange", -4, R, KEY),
+    command("xrevrange", -4, R, KEY),
+    command("xdel", -3, WF, KEY),
+    command("xtrim", -4, W, KEY),
+    command(
+        "xread",
+        -4,
+        R.union(CommandFlags::BLOCKING)
+            .union(CommandFlags::MOVABLE_KEYS),
+        XREAD_KEYS,
+    ),
+    command(
+        "xreadgroup",
+        -7,
+        WB.union(CommandFlags::MOVABLE_KEYS),
+        XREADGROUP_KEYS,
+    ),
+    command("eval", -3, SCRIPT, SCRIPT_KEYS),
+    command("evalsha", -3, SCRIPT, SCRIPT_KEYS),
+    command("eval_ro", -3, SCRIPT_RO, SCRIPT_KEYS),
+    command("evalsha_ro", -3, SCRIPT_RO, SCRIPT_KEYS),
+    command("fcall", -3, SCRIPT, SCRIPT_KEYS),
+    command("fcall_ro", -3, SCRIPT_RO, SCRIPT_KEYS),
+    command(
+        "watch",
+        -2,
+        CommandFlags::NOSCRIPT.union(CommandFlags::FAST),
+        ALL_KEYS,
+    ),
+    command("subscribe", -2, PUBSUB, NO_KEYS),
+    command("psubscribe", -2, PUBSUB, NO_KEYS),
+    command("unsubscribe", -1, PUBSUB, NO_KEYS),
+    command("punsubscribe", -1, PUBSUB, NO_KEYS),
+    command("ssubscribe", -2, PUBSUB, ALL_KEYS),
+    command("sunsubscribe", -1, PUBSUB, ALL_KEYS),
+    command(
+        "publish",
+        3,
+        CommandFlags::PUBSUB.union(CommandFlags::FAST),
+        NO_KEYS,
+    ),
+    command(
+        "spublish",
+        3,
+        CommandFlags::PUBSUB.union(CommandFlags::FAST),
+        KEY,
+    ),
+    command("ping", -1, CommandFlags::FAST, NO_KEYS),
+    command("echo", 2, CommandFlags::FAST, NO_KEYS),
+    command("select", 2, CONNECTION, NO_KEYS),
+    command(
+        "auth",
+        -2,
+        CONNECTION
+            .union(CommandFlags::NOSCRIPT)
+            .union(CommandFlags::NO_AUTH),
+        NO_KEYS,
+    ),
+    command(
+        "hello",
+        -1,
+        CONNECTION
+            .union(CommandFlags::NOSCRIPT)
+            .union(CommandFlags::NO_AUTH),
+        NO_KEYS,
+    ),
+    command(
+        "multi",
+        1,
+        CommandFlags::NOSCRIPT.union(CommandFlags::FAST),
+        NO_KEYS,
+    ),
+    command("exec", 1, CommandFlags::NOSCRIPT, NO_KEYS),
+    command(
+        "discard",
+        1,
+        CommandFlags::NOSCRIPT.union(CommandFlags::FAST),
+        NO_KEYS,
+    ),
+    command("dbsize", 1, RF, NO_KEYS),
+    command("keys", 2, R, NO_KEYS),
+    command("scan", -2, R, NO_KEYS),
+    command("randomkey", 1, R, NO_KEYS),
+    command("flushall", -1, W, NO_KEYS),
+    command("flushdb", -1, W, NO_KEYS),
+    command(
+        "info",
+        -1,
+        CommandFlags::LOADING.union(CommandFlags::STALE),
+        NO_KEYS,
+    ),
+    command("time", 1, CONNECTION, NO_KEYS),
+    command("client", -2, CommandFlags::empty(), NO_KEYS),
+    command("config", -2, CommandFlags::empty(), NO_KEYS),
+    command(
+        "command",
+        -1,
+        CommandFlags::LOADING.union(CommandFlags::STALE),
+        NO_KEYS,
+    ),
+];
+
+/// The built-in table, built once on first use.
+pub fn builtin_table() -> &'static CommandTable {
+    static TABLE: OnceLock<CommandTable> = OnceLock::new();
+    TABLE.get_or_init(CommandTable::builtin)
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandTable {
+    version: Cow<'static, str>,
+    commands: HashMap<Cow<'static, str>, CommandSpec>,
+}
+
+impl Default for CommandTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandTable {
+    pub fn new() -> Self {
+        Self {
+            version: Cow::Borrowed(""),
+            commands: HashMap::new(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        let mut table = Self::new();
+        table.version = Cow::Borrowed(BUILTIN_VERSION);
+        for spec in BUILTIN_COMMANDS {
+            table.insert(spec.clone());
+        }
+        table
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn set_version(&mut self, version: impl Into<Cow<'static, str>>) {
+        self.version = version.into();
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn insert(&mut self, spec: CommandSpec) -> Option<CommandSpec> {
+        self.commands.insert(spec.name.clone(), spec)
+    }
+
+    /// Looks a command up by name, ignoring case.
+    pub fn get(&self, name: &[u8]) -> Option<&CommandSpec> {
+        let mut lower = [0u8; 32];
+        let name = match lower.get_mut(..name.len()) {
+            Some(lower) => {
+                lower.copy_from_slice(name);
+                lower.make_ascii_lowercase();
+                std::str::from_utf8(lower).ok()?
+            }
+            None => return None,
+        };
+        self.commands.get(name)
+    }
+
+    /// Looks up the command of a decoded request and checks its arity.
+    pub fn validate(&self, request: &V2Frame<'_>) -> Result<&CommandSpec, Error> {
+        let args = request_args(request).ok_or(Error::InvalidCommand)?;
+        let name = args.first().ok_or(Error::InvalidCommand)?;
+        let spec = self.get(name).ok_or(Error::UnknownCommand)?;
+        if spec.check_arity(args.len()) {
+            Ok(spec)
+        } else {
+            Err(Error::WrongArity)
+        }
+    }
+
+    /// Extracts the key arguments of a decoded request. Returns `None` when the
+    /// frame is not a command, the command is not known or its arguments hold
+    /// fewer keys than they declare.
+    pub fn command_keys<'a>(&self, request: &V2Frame<'a>) -> Option<Vec<&'a [u8]>> {
+        let args = request_args(request)?;
+        let spec = self.get(args.first()?)?;
+        spec.keys(&args)
+    }
+
+    /// Adds or replaces the commands described by a `COMMAND INFO` reply,
+    /// returning how many were updated. Unknown commands, which the server
+    /// reports as null, are skipped.
+    pub fn update_from_command_info(&mut self, reply: &V3Frame<'_>) -> Result<usize, Error> {
+        let V3Frame::Array { data, .. } = reply else {
+            return Err(Error::InvalidCommandInfo);
+        };
+
+        let mut updated = 0;
+        for info in data.iter() {
+            if let V3Frame::Null { .. } = info {
+                continue;
+            }
+            let spec = parse_command_info(info).ok_or(Error::InvalidCommandInfo)?;
+            self.insert(spec);
+            updated += 1;
+        }
+        Ok(updated)
+    }
+}
+
+fn request_args<'a>(request: &V2Frame<'a>) -> Option<Vec<&'a [u8]>> {
+    match request {
+        V2Frame::Array(data) => data.iter().map(|arg| arg.as_bytes()).collect(),
+        _ => None,
+    }
+}
+
+fn frame_text(frame: &V3Frame<'_>) -> Option<String> {
+    let text = frame.as_bytes()?;
+    String::from_utf8(text.to_ascii_lowercase()).ok()
+}
+
+fn frame_integer(frame: &V3Frame<'_>) -> Option<isize> {
+    match frame {
+        V3Frame::Integer { data, .. } => Some(*data),
+        _ => None,
+    }
+}
+
+/// Reads a count or position, which a negative value cannot be.
+fn frame_usize(frame: &V3Frame<'_>) -> Option<usize> {
+    usize::try_from(frame_integer(frame)?).ok()
+}
+
+fn frame_elements<'f, 'a>(frame: &'f V3Frame<'a>) -> Option<Vec<&'f V3Frame<'a>>> {
+    match frame {
+        V3Frame::Array { data, .. } => Some(data.iter().collect()),
+        V3Frame::Set { data, .. } => Some(data.iter().collect()),
+        _ => None,
+    }
+}
+
+/// Looks up a field of a RESP3 map, or of the flat key/value array a RESP2
+/// server replies with instead.
+fn frame_field<'f, 'a>(frame: &'f V3Frame<'a>, name: &[u8]) -> Option<&'f V3Frame<'a>> {
+    match frame {
+        V3Frame::Map { data, .. } => data
+            .iter()
+            .find(|(key, _)| key.as_bytes() == Some(name))
+            .map(|(_, value)| value),
+        V3Frame::Array { data, .. } => data
+            .chunks_exact(2)
+            .find(|pair| pair[0].as_bytes() == Some(name))
+            .map(|pair| &pair[1]),
+        _ => None,
+    }
+}
+
+fn parse_command_info(info: &V3Frame<'_>) -> Option<CommandSpec> {
+    let fields = frame_elements(info)?;
+    let name = frame_text(fields.first()?)?;
+    let arity = frame_integer(fields.get(1)?)? as i64;
+    let flags = frame_elements(fields.get(2)?)?
+        .into_iter()
+        .filter_map(|flag| CommandFlags::from_name(flag.as_bytes()?))
+        .fold(CommandFlags::empty(), CommandFlags::union);
+
+    let key_specs = match fields.get(8) {
+        Some(specs) => frame_elements(specs)?
+            .into_iter()
+            .map(parse_key_spec)
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect(),
+        // Servers before 7.0 only report the legacy first, last and step.
+        None => {
+            let first = frame_integer(fields.get(3)?)?;
+            let last = frame_integer(fields.get(4)?)?;
+            let step = frame_usize(fields.get(5)?)?;
+            if first > 0 {
+                let last_key = if last < 0 { last } else { last - first };
+                vec![KeySpec::range(first as usize, last_key, step)]
+            } else {
+                Vec::new()
+            }
+        }
+    };
+
+    Some(CommandSpec {
+        name: Cow::Owned(name),
+        arity,
+        flags,
+        key_specs: Cow::Owned(key_specs),
+    })
+}
+
+/// Parses one key specification. Specifications of type `unknown` yield
+/// `Ok(None)` and are skipped.
+fn parse_key_spec(spec: &V3Frame<'_>) -> Option<Option<KeySpec>> {
+    let begin = frame_field(spec, b"begin_search")?;
+    let begin_spec = frame_field(begin, b"spec")?;
+    let begin_search = match frame_field(begin, b"type")?.as_bytes()? {
+        b"index" => BeginSearch::Index(frame_usize(frame_field(begin_spec, b"index")?)?),
+        b"keyword" => BeginSearch::Keyword {
+            keyword: Cow::Owned(
+                String::from_utf8(frame_field(begin_spec, b"keyword")?.as_bytes()?.to_vec())
+                    .ok()?,
+            ),
+            start_from: frame_integer(frame_field(begin_spec, b"startfrom")?)?,
+        },
+        _ => return Some(None),
+    };
+
+    let find = frame_field(spec, b"find_keys")?;
+    let find_spec = frame_field(find, b"spec")?;
+    let find_keys = match frame_field(find, b"type")?.as_bytes()? {
+        b"range" => FindKeys::Range {
+            last_key: frame_integer(frame_field(find_spec, b"lastkey")?)?,
+            step: frame_usize(frame_field(find_spec, b"keystep")?)?,
+            limit: frame_usize(frame_field(find_spec, b"limit")?)?,
+        },
+        b"keynum" => FindKeys::Keynum {
+            keynum_index: frame_usize(frame_field(find_spec, b"keynumidx")?)?,
+            first_key: frame_usize(frame_field(find_spec, b"firstkey")?)?,
+            step: frame_usize(frame_field(find_spec, b"keystep")?)?,
+        },
+        _ => return Some(None),
+    };
+
+    Some(Some(KeySpec {
+        begin_search,
+        find_keys,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{decode, v3::V3};
+    use minivec::{mini_vec, MiniVec};
+
+    fn request<'a>(args: &[&'a [u8]]) -> V2Frame<'a> {
+        V2Frame::Array(
+            args.iter()
+                .map(|arg| V2Frame::BulkString(arg))
+                .collect::<MiniVec<_>>(),
+        )
+    }
+
+    #[test]
+    fn test_validate() {
+        let table = CommandTable::builtin();
+
+        let spec = table.validate(&request(&[b"GET", b"foo"])).unwrap();
+        assert_eq!(spec.name, "get");
+        assert!(spec.flags.contains(CommandFlags::READONLY));
+        assert!(!spec.flags.contains(CommandFlags::WRITE));
+
+        assert_eq!(
+            table.validate(&request(&[b"GET", b"foo", b"bar"])),
+            Err(Error::WrongArity)
+        );
+        assert_eq!(
+            table.validate(&request(&[b"SET", b"foo"])),
+            Err(Error::WrongArity)
+        );
+        assert!(table
+            .validate(&request(&[b"SET", b"foo", b"bar", b"EX", b"10"]))
+            .is_ok());
+        assert_eq!(
+            table.validate(&request(&[b"NOSUCHCOMMAND"])),
+            Err(Error::UnknownCommand)
+        );
+        assert_eq!(
+            table.validate(&V2Frame::SimpleString(b"GET")),
+            Err(Error::InvalidCommand)
+        );
+    }
+
+    #[test]
+    fn test_command_keys() {
+        let table = CommandTable::builtin();
+
+        let keys = table.command_keys(&request(&[
+            b"XREADGROUP",
+            b"GROUP",
+            b"g",
+            b"c",
+            b"STREAMS",
+            b"s1",
+            b"s2",
+            b">",
+            b">",
+        ]));
+        assert_eq!(keys, Some(vec![&b"s1"[..], b"s2"]));
+
+        let keys = table.command_keys(&request(&[b"ZUNIONSTORE", b"dst", b"2", b"a", b"b"]));
+        assert_eq!(keys, Some(vec![&b"dst"[..], b"a", b"b"]));
+
+        let keys = table.command_keys(&request(&[b"SUBSCRIBE", b"news"]));
+        assert_eq!(keys, Some(vec![]));
+    }
+
+    #[test]
+    fn test_update_from_command_info() {
+        let input = b"*2\r\n\
+            *10\r\n$3\r\nget\r\n:2\r\n~2\r\n+readonly\r\n+fast\r\n:1\r\n:1\r\n:1\r\n\
+            ~2\r\n+@read\r\n+@string\r\n~0\r\n\
+            *1\r\n%3\r\n\
+            $5\r\nflags\r\n~2\r\n+RO\r\n+access\r\n\
+            $12\r\nbegin_search\r\n%2\r\n$4\r\ntype\r\n$5\r\nindex\r\n$4\r\nspec\r\n%1\r\n$5\r\nindex\r\n:1\r\n\
+            $9\r\nfind_keys\r\n%2\r\n$4\r\ntype\r\n$5\r\nrange\r\n$4\r\nspec\r\n%3\r\n\
+            $7\r\nlastkey\r\n:0\r\n$7\r\nkeystep\r\n:1\r\n$5\r\nlimit\r\n:0\r\n\
+            *0\r\n\
+            _\r\n";
+        let (reply, _) = decode::<V3>(input).unwrap().unwrap();
+
+        let mut table = CommandTable::new();
+        assert_eq!(table.update_from_command_info(&reply), Ok(1));
+        let spec = table.get(b"GET").unwrap();
+        assert_eq!(spec, &command("get", 2, RF, KEY));
+
+        let invalid = V3Frame::Array {
+            data: mini_vec![V3Frame::Integer {
+                data: 1,
+                attributes: None,
+            }],
+            attributes: None,
+        };
+        assert_eq!(
+            table.update_from_command_info(&invalid),
+            Err(Error::InvalidCommandInfo)
+        );
+
+        let negative = std::str::from_utf8(input)
+            .unwrap()
+            .replace("keystep\r\n:1", "keystep\r\n:-1");
+        let (reply, _) = decode::<V3>(negative.as_bytes()).unwrap().unwrap();
+        assert_eq!(
+            table.update_from_command_info(&reply),
+            Err(Error::InvalidCommandInfo)
+        );
+    }
+}
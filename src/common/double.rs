@@ -0,0 +1,115 @@
+use lexical::{format::STANDARD, parse_with_options, ParseFloatOptions, ToLexical, BUFFER_SIZE};
+
+use super::Error;
+
+/// Parses a RESP3 double, which is either `inf`, `-inf`, `nan` or
+/// `[+|-]<integral>[.<fractional>][<e|E>[+|-]<exponent>]`.
+///
+/// The syntax is checked here, since `lexical` on its own also takes forms
+/// Redis never sends, such as `NaN`, `Infinity` or `.5`.
+pub(crate) fn parse_double(bytes: &[u8]) -> Result<f64, Error> {
+    match bytes {
+        b"inf" => return Ok(f64::INFINITY),
+        b"-inf" => return Ok(f64::NEG_INFINITY),
+        b"nan" => return Ok(f64::NAN),
+        _ => {}
+    }
+
+    let rest = match bytes.split_first() {
+        Some((b'+' | b'-', rest)) => rest,
+        _ => bytes,
+    };
+    let rest = digits(rest).ok_or(Error::InvalidDouble)?;
+    let rest = match rest.split_first() {
+        Some((b'.', fraction)) => digits(fraction).ok_or(Error::InvalidDouble)?,
+        _ => rest,
+    };
+    let rest = match rest.split_first() {
+        Some((b'e' | b'E', exponent)) => {
+            let exponent = match exponent.split_first() {
+                Some((b'+' | b'-', exponent)) => exponent,
+                _ => exponent,
+            };
+            digits(exponent).ok_or(Error::InvalidDouble)?
+        }
+        _ => rest,
+    };
+    if !rest.is_empty() {
+        return Err(Error::InvalidDouble);
+    }
+
+    let options = ParseFloatOptions::new();
+    parse_with_options::<f64, _, STANDARD>(bytes, &options).map_err(|_| Error::InvalidDouble)
+}
+
+/// Skips a non-empty run of digits, returning what follows it.
+fn digits(bytes: &[u8]) -> Option<&[u8]> {
+    let count = bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_digit())
+        .count();
+    (count > 0).then(|| &bytes[count..])
+}
+
+/// Formats `num` the way RESP3 spells it, using the shortest text that
+/// parses back to the same value.
+pub(crate) fn format_double(num: f64, bytes: &mut [u8; BUFFER_SIZE]) -> &[u8] {
+    if num.is_nan() {
+        b"nan"
+    } else if num == f64::INFINITY {
+        b"inf"
+    } else if num == f64::NEG_INFINITY {
+        b"-inf"
+    } else {
+        num.to_lexical(bytes)
+    }
+}
+
+pub(crate) fn double_len(num: f64) -> usize {
+    let mut bytes = [0u8; BUFFER_SIZE];
+    format_double(num, &mut bytes).len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_double() {
+        assert_eq!(parse_double(b"1.23"), Ok(1.23));
+        assert_eq!(parse_double(b"-1.23e-10"), Ok(-1.23e-10));
+        assert_eq!(parse_double(b"+10E+2"), Ok(1000.0));
+        assert_eq!(parse_double(b"10"), Ok(10.0));
+        assert_eq!(parse_double(b"inf"), Ok(f64::INFINITY));
+        assert_eq!(parse_double(b"-inf"), Ok(f64::NEG_INFINITY));
+        assert!(parse_double(b"nan").unwrap().is_nan());
+
+        for invalid in [
+            b"".as_slice(),
+            b"-",
+            b".5",
+            b"1.",
+            b"1e",
+            b"1e+",
+            b"1.5x",
+            b"NaN",
+            b"Infinity",
+            b"+inf",
+            b"-nan",
+        ] {
+            assert_eq!(parse_double(invalid), Err(Error::InvalidDouble));
+        }
+    }
+
+    #[test]
+    fn test_format_double() {
+        let mut bytes = [0u8; BUFFER_SIZE];
+        assert_eq!(format_double(f64::NAN, &mut bytes), b"nan");
+        assert_eq!(format_double(f64::INFINITY, &mut bytes), b"inf");
+        assert_eq!(format_double(f64::NEG_INFINITY, &mut bytes), b"-inf");
+        for num in [0.0, -0.0, 1.5, 1.23e-10, 1e300, f64::MIN_POSITIVE, f64::MAX] {
+            let text = format_double(num, &mut bytes);
+            assert_eq!(parse_double(text).map(f64::to_bits), Ok(num.to_bits()));
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use std::io::{Result as IoResult, Write};
 
-use super::Error;
+use bytes::BufMut;
+use lexical::{ToLexical, BUFFER_SIZE};
+
+use super::{Error, VectoredEncoder};
 
 pub trait Encoder {
     type Frame<'a>: EncodeLen;
@@ -20,3 +23,40 @@ pub trait EncodeWithWriter {
     where
         W: Write;
 }
+
+pub trait EncodeVectored {
+    type Frame<'a>: EncodeLen;
+
+    fn encode_vectored<'a>(frame: Self::Frame<'a>, encoder: &mut VectoredEncoder<'a>);
+}
+
+pub trait EncodeToBuf {
+    type Frame<'a>: EncodeLen;
+
+    fn encode_to_buf<B>(frame: Self::Frame<'_>, buf: &mut B)
+    where
+        B: BufMut;
+}
+
+/// Formats `num` on the stack rather than through an intermediate `String`.
+pub(crate) fn put_number<B, N>(buf: &mut B, num: N)
+where
+    B: BufMut,
+    N: ToLexical,
+{
+    let mut bytes = [0u8; BUFFER_SIZE];
+    buf.put_slice(num.to_lexical(&mut bytes));
+}
+
+/// Number of bytes `num` takes in decimal, without formatting it.
+pub(crate) fn uint_len(num: usize) -> usize {
+    num.checked_ilog10().map_or(1, |digits| digits as usize + 1)
+}
+
+pub(crate) fn int_len(num: i64) -> usize {
+    let sign = usize::from(num < 0);
+    sign + num
+        .unsigned_abs()
+        .checked_ilog10()
+        .map_or(1, |digits| digits as usize + 1)
+}
@@ -24,9 +24,39 @@ pub enum Error {
     #[error("invalid boolean")]
     InvalidBoolean,
 
+    #[error("invalid null")]
+    InvalidNull,
+
+    #[error("invalid double")]
+    InvalidDouble,
+
+    #[error("invalid big number")]
+    InvalidBigNumber,
+
+    #[error("invalid verbatim string")]
+    InvalidVerbatimString,
+
     #[error("invalid map")]
     InvalidMap,
 
     #[error("invalid set")]
     InvalidSet,
+
+    #[error("nesting too deep")]
+    TooDeep,
+
+    #[error("invalid command")]
+    InvalidCommand,
+
+    #[error("unknown command")]
+    UnknownCommand,
+
+    #[error("wrong number of arguments")]
+    WrongArity,
+
+    #[error("invalid command info")]
+    InvalidCommandInfo,
+
+    #[error("invalid type")]
+    InvalidType,
 }
@@ -0,0 +1,94 @@
+use memchr::memmem;
+
+use super::{Error, MAX_DEPTH};
+
+/// Measures the next frame in a buffer without decoding it.
+pub trait FrameLen {
+    /// Returns the encoded length of the first frame in `input`, `None` if
+    /// the frame is not complete yet, or an error if it is malformed.
+    fn frame_len(input: &[u8]) -> Result<Option<usize>, Error>;
+
+    /// Like [`FrameLen::frame_len`], but picks up where `scan` stopped on an
+    /// earlier, shorter prefix of the same `input`, so a frame arriving in
+    /// many reads is measured once rather than once per read.
+    ///
+    /// `scan` must be reset before measuring another frame, including after
+    /// this returns a length or an error. The default rescans from the start.
+    fn resume_frame_len(scan: &mut FrameScan, input: &[u8]) -> Result<Option<usize>, Error> {
+        let _ = scan;
+        Self::frame_len(input)
+    }
+}
+
+/// How far [`FrameLen::resume_frame_len`] got through a partial frame.
+#[derive(Debug, Clone)]
+pub struct FrameScan {
+    /// Where the first line not measured yet starts.
+    pub(crate) position: usize,
+    /// The type byte of each open aggregate and the values it still owes,
+    /// above the one value owed for the frame itself.
+    pub(crate) levels: [(u8, usize); MAX_DEPTH + 1],
+    pub(crate) depth: usize,
+    /// Whether attributes were read for the value that comes next.
+    pub(crate) annotated: bool,
+}
+
+impl FrameScan {
+    pub fn new() -> Self {
+        let mut levels = [(0, 0); MAX_DEPTH + 1];
+        levels[0].1 = 1;
+        Self {
+            position: 0,
+            levels,
+            depth: 0,
+            annotated: false,
+        }
+    }
+
+    /// Forgets the frame measured so far, so the next one starts afresh.
+    pub fn reset(&mut self) {
+        self.position = 0;
+        self.levels[0] = (0, 1);
+        self.depth = 0;
+        self.annotated = false;
+    }
+}
+
+impl Default for FrameScan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A line's type byte, the rest of the line, and the position past its CRLF.
+pub(crate) type Line<'a> = (u8, &'a [u8], usize);
+
+/// Splits the line starting at `position`, or returns `None` if its CRLF has
+/// not arrived yet.
+#[inline]
+pub(crate) fn line(input: &[u8], position: usize) -> Option<Result<Line<'_>, Error>> {
+    let rest = input.get(position..)?;
+    let end = memmem::find(rest, b"\r\n")?;
+    Some(match rest[..end].split_first() {
+        Some((first, follow)) => Ok((*first, follow, position + end + 2)),
+        None => Err(Error::Unknown),
+    })
+}
+
+/// Skips a length-prefixed payload and its CRLF, reporting `error` when the
+/// trailer is not where the length says it is.
+#[inline]
+pub(crate) fn skip_payload(
+    input: &[u8],
+    position: usize,
+    len: usize,
+    error: Error,
+) -> Result<Option<usize>, Error> {
+    let end = position.checked_add(len).ok_or(error)?;
+    match input.get(end..end.saturating_add(2)) {
+        Some(b"\r\n") => Ok(Some(end + 2)),
+        Some(_) => Err(error),
+        None if input.len() > end && input[end] != b'\r' => Err(error),
+        None => Ok(None),
+    }
+}
@@ -1,7 +1,31 @@
+mod double;
 mod encode;
 mod error;
+mod frame_len;
 mod parser;
+mod push;
+mod reader;
+mod redis_error;
+mod scan;
+mod stream;
+mod vectored;
+mod writer;
 
-pub use encode::{EncodeLen, EncodeWithWriter, Encoder};
+pub(crate) use double::{double_len, format_double, parse_double};
+pub(crate) use encode::{int_len, put_number, uint_len};
+pub use encode::{EncodeLen, EncodeToBuf, EncodeVectored, EncodeWithWriter, Encoder};
 pub use error::Error;
-pub use parser::{ParseIter, Parser, Remaining};
+pub(crate) use frame_len::{line, skip_payload};
+pub use frame_len::{FrameLen, FrameScan};
+pub use parser::{ParseIter, Parser, Remaining, MAX_DEPTH};
+pub use push::{Invalidation, Message};
+pub(crate) use push::{INVALIDATE, INVALIDATE_CHANNEL};
+pub use reader::FrameReader;
+pub use redis_error::{Redirect, RedisError, RedisErrorKind};
+pub(crate) use scan::{parse_decimal, CrlfScanner};
+pub use stream::{
+    write_bulk_from_chunks, write_bulk_from_reader, write_streamed_string, BulkDecoder, BulkEvent,
+    BulkHeader, BulkWriter, StreamedStringWriter,
+};
+pub use vectored::VectoredEncoder;
+pub use writer::FrameWriter;
@@ -1,5 +1,11 @@
 use super::error::Error;
 
+/// The deepest nesting of aggregates, attributes included, that decoding
+/// accepts. Dropping, comparing and encoding a frame recurse into its
+/// elements, so deeper input fails with [`Error::TooDeep`] rather than
+/// building a frame that would overflow the stack later.
+pub const MAX_DEPTH: usize = 256;
+
 pub trait Parser {
     type Frame<'a>;
 
@@ -16,3 +22,9 @@ pub trait ParseIter {
 
     fn parse_iter<'a>(input: &'a [u8]) -> Self::Iter<'a>;
 }
+
+impl<F> Remaining for (F, usize) {
+    fn remaining(&self) -> usize {
+        self.1
+    }
+}
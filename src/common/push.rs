@@ -0,0 +1,32 @@
+/// A decoded frame, classified by whether the server pushed it out of band
+/// or sent it as the reply to a request.
+#[derive(Debug, PartialEq)]
+pub enum Message<F> {
+    Push(F),
+    Reply(F),
+}
+
+impl<F> Message<F> {
+    pub fn is_push(&self) -> bool {
+        matches!(self, Self::Push(_))
+    }
+
+    pub fn into_inner(self) -> F {
+        match self {
+            Self::Push(frame) | Self::Reply(frame) => frame,
+        }
+    }
+}
+
+/// A client-side caching invalidation message, as sent by the server after
+/// `CLIENT TRACKING ON`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Invalidation<'a> {
+    /// The listed keys were modified.
+    Keys(Vec<&'a [u8]>),
+    /// The whole keyspace was flushed and every cached key must be dropped.
+    Flush,
+}
+
+pub(crate) const INVALIDATE: &[u8] = b"invalidate";
+pub(crate) const INVALIDATE_CHANNEL: &[u8] = b"__redis__:invalidate";
@@ -0,0 +1,174 @@
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+
+use super::{BulkDecoder, BulkEvent, BulkHeader, Error, FrameLen, FrameScan, Parser};
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Reads frames one by one from any [`Read`], buffering only as much input as
+/// the next frame needs.
+///
+/// Decoded frames borrow the reader's buffer, so each one must be dropped
+/// before the next call to [`FrameReader::read_frame`].
+#[derive(Debug)]
+pub struct FrameReader<R, P> {
+    reader: R,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    consumed: usize,
+    /// How much of the frame at `start` has been measured, so each read only
+    /// scans the bytes it added.
+    scan: FrameScan,
+    parser: std::marker::PhantomData<P>,
+}
+
+impl<R, P> FrameReader<R, P>
+where
+    R: Read,
+    P: Parser + FrameLen,
+{
+    pub fn new(reader: R) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, reader)
+    }
+
+    pub fn with_capacity(capacity: usize, reader: R) -> Self {
+        Self {
+            reader,
+            buf: vec![0; capacity.max(1)],
+            start: 0,
+            end: 0,
+            consumed: 0,
+            scan: FrameScan::new(),
+            parser: std::marker::PhantomData,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Bytes read from the inner reader but not decoded yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.start + self.consumed..self.end]
+    }
+
+    /// Returns the next frame, or `None` once the reader is exhausted at a
+    /// frame boundary.
+    pub fn read_frame(&mut self) -> IoResult<Option<P::Frame<'_>>> {
+        self.start += self.consumed;
+        self.consumed = 0;
+
+        let consumed = loop {
+            match P::resume_frame_len(&mut self.scan, &self.buf[self.start..self.end]) {
+                Ok(Some(len)) => break len,
+                Ok(None) | Err(Error::NotComplete) => {}
+                Err(e) => {
+                    self.scan.reset();
+                    return Err(IoError::new(ErrorKind::InvalidData, e));
+                }
+            }
+            if self.fill()? == 0 {
+                self.scan.reset();
+                return if self.start == self.end {
+                    Ok(None)
+                } else {
+                    Err(IoError::new(ErrorKind::UnexpectedEof, Error::NotComplete))
+                };
+            }
+        };
+
+        self.scan.reset();
+        self.consumed = consumed;
+        P::parse(&self.buf[self.start..self.start + consumed])
+            .map_err(|e| IoError::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Streams the next reply, which must be a bulk string or null, into
+    /// `sink` chunk by chunk instead of buffering it whole. Returns the
+    /// payload length, or `None` for a null reply.
+    ///
+    /// An error reply is consumed, so the next reply can still be read, and
+    /// comes back as an error of kind `Other` holding the server's message.
+    pub fn read_bulk<W>(&mut self, sink: &mut W) -> IoResult<Option<u64>>
+    where
+        W: Write,
+    {
+        self.start += self.consumed;
+        self.consumed = 0;
+        self.scan.reset();
+
+        let mut decoder = BulkDecoder::new();
+        let mut len = Some(0);
+        let mut message = None;
+        loop {
+            let decoded = decoder
+                .decode(&self.buf[self.start..self.end])
+                .map_err(|e| IoError::new(ErrorKind::InvalidData, e))?;
+            match decoded {
+                Some((event, used)) => {
+                    match event {
+                        BulkEvent::Header(BulkHeader::Null) => len = None,
+                        BulkEvent::Header(BulkHeader::Error(len)) => {
+                            message = Some(Vec::with_capacity(len.min(self.buf.len())));
+                        }
+                        BulkEvent::Header(_) => {}
+                        BulkEvent::Chunk(chunk) => match &mut message {
+                            Some(message) => message.extend_from_slice(chunk),
+                            None => {
+                                sink.write_all(chunk)?;
+                                len = len.map(|len| len + chunk.len() as u64);
+                            }
+                        },
+                        BulkEvent::End => {
+                            self.start += used;
+                            return match message {
+                                Some(message) => Err(IoError::other(
+                                    String::from_utf8_lossy(&message).into_owned(),
+                                )),
+                                None => Ok(len),
+                            };
+                        }
+                    }
+                    self.start += used;
+                }
+                None => {
+                    if self.fill()? == 0 {
+                        return Err(IoError::new(ErrorKind::UnexpectedEof, Error::NotComplete));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads more input, first reclaiming the space of consumed frames and
+    /// only growing the buffer once it is full of a single partial frame.
+    fn fill(&mut self) -> IoResult<usize> {
+        if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+        if self.end == self.buf.len() {
+            self.buf.resize(self.buf.len() * 2, 0);
+        }
+
+        loop {
+            match self.reader.read(&mut self.buf[self.end..]) {
+                Ok(read) => {
+                    self.end += read;
+                    return Ok(read);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use lexical::parse;
+
+/// An error reply from the server, split into its code prefix and message.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RedisError<'a> {
+    code: &'a [u8],
+    message: &'a [u8],
+    kind: RedisErrorKind<'a>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RedisErrorKind<'a> {
+    Moved(Redirect<'a>),
+    Ask(Redirect<'a>),
+    TryAgain,
+    ClusterDown,
+    WrongType,
+    NoScript,
+    Busy,
+    Loading,
+    ReadOnly,
+    NoAuth,
+    Other,
+}
+
+/// The target of a `MOVED` or `ASK` redirect. An empty host means the
+/// client should reuse the host of the node that sent the redirect.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Redirect<'a> {
+    pub slot: u16,
+    pub host: &'a [u8],
+    pub port: u16,
+}
+
+impl<'a> Redirect<'a> {
+    fn parse(message: &'a [u8]) -> Option<Self> {
+        let (slot, endpoint) = split_word(message);
+        let colon = endpoint.iter().rposition(|byte| *byte == b':')?;
+        let (host, port) = (&endpoint[..colon], &endpoint[colon + 1..]);
+
+        Some(Self {
+            slot: parse(slot).ok()?,
+            host,
+            port: parse(port).ok()?,
+        })
+    }
+}
+
+impl<'a> RedisError<'a> {
+    pub fn parse(data: &'a [u8]) -> Self {
+        let (code, message) = split_word(data);
+        let kind = match code {
+            b"MOVED" => {
+                Redirect::parse(message).map_or(RedisErrorKind::Other, RedisErrorKind::Moved)
+            }
+            b"ASK" => Redirect::parse(message).map_or(RedisErrorKind::Other, RedisErrorKind::Ask),
+            b"TRYAGAIN" => RedisErrorKind::TryAgain,
+            b"CLUSTERDOWN" => RedisErrorKind::ClusterDown,
+            b"WRONGTYPE" => RedisErrorKind::WrongType,
+            b"NOSCRIPT" => RedisErrorKind::NoScript,
+            b"BUSY" => RedisErrorKind::Busy,
+            b"LOADING" => RedisErrorKind::Loading,
+            b"READONLY" => RedisErrorKind::ReadOnly,
+            b"NOAUTH" => RedisErrorKind::NoAuth,
+            _ => RedisErrorKind::Other,
+        };
+
+        Self {
+            code,
+            message,
+            kind,
+        }
+    }
+
+    pub fn code(&self) -> &'a [u8] {
+        self.code
+    }
+
+    pub fn message(&self) -> &'a [u8] {
+        self.message
+    }
+
+    pub fn kind(&self) -> RedisErrorKind<'a> {
+        self.kind
+    }
+
+    pub fn redirect(&self) -> Option<Redirect<'a>> {
+        match self.kind {
+            RedisErrorKind::Moved(redirect) | RedisErrorKind::Ask(redirect) => Some(redirect),
+            _ => None,
+        }
+    }
+
+    /// Errors caused by a transient server or cluster state, where sending the
+    /// same command again later may succeed.
+    pub fn is_retriable(&self) -> bool {
+        matches!(
+            self.kind,
+            RedisErrorKind::TryAgain
+                | RedisErrorKind::ClusterDown
+                | RedisErrorKind::Busy
+                | RedisErrorKind::Loading
+        )
+    }
+}
+
+impl fmt::Display for RedisError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.code))?;
+        if !self.message.is_empty() {
+            write!(f, " {}", String::from_utf8_lossy(self.message))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RedisError<'_> {}
+
+fn split_word(data: &[u8]) -> (&[u8], &[u8]) {
+    match data.iter().position(|byte| *byte == b' ') {
+        Some(position) => (&data[..position], &data[position + 1..]),
+        None => (data, &[]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redirect() {
+        let error = RedisError::parse(b"MOVED 3999 127.0.0.1:6381");
+        assert_eq!(error.code(), b"MOVED");
+        assert_eq!(
+            error.kind(),
+            RedisErrorKind::Moved(Redirect {
+                slot: 3999,
+                host: b"127.0.0.1",
+                port: 6381,
+            })
+        );
+
+        let error = RedisError::parse(b"ASK 12182 ::1:7000");
+        assert_eq!(
+            error.redirect(),
+            Some(Redirect {
+                slot: 12182,
+                host: b"::1",
+                port: 7000,
+            })
+        );
+
+        let error = RedisError::parse(b"MOVED 3999 :6380");
+        assert_eq!(
+            error.redirect().map(|redirect| redirect.host),
+            Some(&b""[..])
+        );
+
+        let error = RedisError::parse(b"MOVED nonsense");
+        assert_eq!(error.kind(), RedisErrorKind::Other);
+    }
+
+    #[test]
+    fn test_kind() {
+        let error =
+            RedisError::parse(b"WRONGTYPE Operation against a key holding the wrong kind of value");
+        assert_eq!(error.kind(), RedisErrorKind::WrongType);
+        assert_eq!(
+            error.message(),
+            b"Operation against a key holding the wrong kind of value"
+        );
+        assert!(!error.is_retriable());
+
+        let error = RedisError::parse(b"TRYAGAIN Multiple keys request during rehashing of slot");
+        assert!(error.is_retriable());
+
+        let error = RedisError::parse(b"LOADING");
+        assert_eq!(error.code(), b"LOADING");
+        assert_eq!(error.message(), b"");
+        assert!(error.is_retriable());
+
+        let error = RedisError::parse(b"ERR unknown command 'foo'");
+        assert_eq!(error.kind(), RedisErrorKind::Other);
+        assert_eq!(error.to_string(), "ERR unknown command 'foo'");
+    }
+}
@@ -0,0 +1,222 @@
+use lexical::{format::STANDARD, parse_with_options, FromLexicalWithOptions, ParseIntegerOptions};
+
+use super::Error;
+
+const CHUNK: usize = 64;
+
+/// Finds CRLF terminators by classifying 64 bytes at a time into a bitmap of
+/// line ends, then handing positions out of the bitmap one by one.
+///
+/// Unlike a plain search for `\r`, the scanner can be moved forward with
+/// [`CrlfScanner::seek`], which lets the lexers step over length-prefixed
+/// payloads instead of treating a CRLF inside them as a terminator.
+#[derive(Debug, Clone)]
+pub(crate) struct CrlfScanner<'a> {
+    input: &'a [u8],
+    /// Offset of the chunk `mask` describes.
+    base: usize,
+    /// Offset of the next chunk to classify.
+    next: usize,
+    /// Bit `i` is set when `input[base + i..]` starts with a CRLF.
+    mask: u64,
+}
+
+impl<'a> CrlfScanner<'a> {
+    pub(crate) fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            base: 0,
+            next: 0,
+            mask: 0,
+        }
+    }
+
+    /// Ignores every line end before `position`.
+    pub(crate) fn seek(&mut self, position: usize) {
+        if position >= self.next {
+            self.base = position;
+            self.next = position;
+            self.mask = 0;
+        } else if position > self.base {
+            self.mask &= u64::MAX << (position - self.base);
+        }
+    }
+}
+
+impl Iterator for CrlfScanner<'_> {
+    type Item = usize;
+
+    /// Returns the position of the `\r` of the next CRLF.
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        while self.mask == 0 {
+            if self.next >= self.input.len() {
+                return None;
+            }
+            self.base = self.next;
+            self.next += CHUNK;
+            self.mask = crlf_mask(self.input, self.base);
+        }
+        let offset = self.mask.trailing_zeros() as usize;
+        self.mask &= self.mask - 1;
+        Some(self.base + offset)
+    }
+}
+
+/// Classifies the 64 bytes at `start`, looking one byte past the chunk for
+/// the `\n` of a CRLF that straddles the boundary.
+#[inline]
+fn crlf_mask(input: &[u8], start: usize) -> u64 {
+    let (cr, lf) = match input.get(start..start + CHUNK) {
+        Some(chunk) => classify(chunk.try_into().unwrap()),
+        None => {
+            let mut chunk = [0; CHUNK];
+            let tail = &input[start..];
+            chunk[..tail.len()].copy_from_slice(tail);
+            classify(&chunk)
+        }
+    };
+    let carry = matches!(input.get(start + CHUNK), Some(b'\n')) as u64;
+    cr & ((lf >> 1) | (carry << (CHUNK - 1)))
+}
+
+/// Bitmaps of the `\r` and `\n` bytes in a chunk.
+#[cfg(target_arch = "x86_64")]
+#[inline]
+fn classify(chunk: &[u8; CHUNK]) -> (u64, u64) {
+    use std::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    // SAFETY: SSE2 is part of the x86_64 baseline, and every load reads 16
+    // bytes from within `chunk`.
+    unsafe {
+        let cr = _mm_set1_epi8(b'\r' as i8);
+        let lf = _mm_set1_epi8(b'\n' as i8);
+        let mut cr_mask = 0;
+        let mut lf_mask = 0;
+        for lane in 0..CHUNK / 16 {
+            let bytes = _mm_loadu_si128(chunk.as_ptr().add(lane * 16) as *const __m128i);
+            let shift = lane * 16;
+            cr_mask |= (_mm_movemask_epi8(_mm_cmpeq_epi8(bytes, cr)) as u16 as u64) << shift;
+            lf_mask |= (_mm_movemask_epi8(_mm_cmpeq_epi8(bytes, lf)) as u16 as u64) << shift;
+        }
+        (cr_mask, lf_mask)
+    }
+}
+
+/// Portable fallback, which locates `\r` with `memchr` like the lexers did
+/// before the bitmap scanner.
+#[cfg(not(target_arch = "x86_64"))]
+#[inline]
+fn classify(chunk: &[u8; CHUNK]) -> (u64, u64) {
+    let cr = memchr::memchr_iter(b'\r', chunk).fold(0, |mask, i| mask | 1 << i);
+    let lf = memchr::memchr_iter(b'\n', chunk).fold(0, |mask, i| mask | 1 << i);
+    (cr, lf)
+}
+
+/// Integers that lengths and RESP integers are parsed into.
+pub(crate) trait Decimal: FromLexicalWithOptions<Options = ParseIntegerOptions> {
+    fn from_digits(value: u64, negative: bool) -> Option<Self>;
+}
+
+impl Decimal for usize {
+    #[inline]
+    fn from_digits(value: u64, negative: bool) -> Option<Self> {
+        usize::try_from(value).ok().filter(|_| !negative)
+    }
+}
+
+impl Decimal for isize {
+    #[inline]
+    fn from_digits(value: u64, negative: bool) -> Option<Self> {
+        let value = isize::try_from(value).ok()?;
+        Some(if negative { -value } else { value })
+    }
+}
+
+impl Decimal for i64 {
+    #[inline]
+    fn from_digits(value: u64, negative: bool) -> Option<Self> {
+        let value = value as i64;
+        Some(if negative { -value } else { value })
+    }
+}
+
+/// Parses a decimal number, with a fast path for the short, plain digit
+/// runs lengths nearly always are. Anything else, including overflow and
+/// malformed input, goes through `lexical` so errors stay the same.
+#[inline]
+pub(crate) fn parse_decimal<N>(bytes: &[u8]) -> Result<N, Error>
+where
+    N: Decimal,
+{
+    let (negative, digits) = match bytes.split_first() {
+        Some((b'-', digits)) => (true, digits),
+        _ => (false, bytes),
+    };
+    // 18 digits always fit an i64, so the fast path cannot overflow.
+    if !digits.is_empty() && digits.len() <= 18 {
+        let value = digits.iter().try_fold(0u64, |value, byte| {
+            let digit = byte.wrapping_sub(b'0');
+            (digit < 10).then_some(value * 10 + digit as u64)
+        });
+        if let Some(number) = value.and_then(|value| N::from_digits(value, negative)) {
+            return Ok(number);
+        }
+    }
+
+    let options = ParseIntegerOptions::new();
+    parse_with_options::<N, _, STANDARD>(bytes, &options).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn positions(input: &[u8]) -> Vec<usize> {
+        CrlfScanner::new(input).collect()
+    }
+
+    #[test]
+    fn test_scanner() {
+        assert_eq!(positions(b""), Vec::<usize>::new());
+        assert_eq!(positions(b"+OK\r\n:1\r\n"), vec![3, 7]);
+        assert_eq!(positions(b"\r\r\n\n\r"), vec![1]);
+
+        let mut input = vec![b'a'; 200];
+        for i in [0, 63, 127, 198] {
+            input[i] = b'\r';
+            input[i + 1] = b'\n';
+        }
+        assert_eq!(positions(&input), vec![0, 63, 127, 198]);
+
+        let mut scanner = CrlfScanner::new(&input);
+        assert_eq!(scanner.next(), Some(0));
+        scanner.seek(100);
+        assert_eq!(scanner.next(), Some(127));
+        scanner.seek(128);
+        assert_eq!(scanner.next(), Some(198));
+        assert_eq!(scanner.next(), None);
+
+        let mut scanner = CrlfScanner::new(b"$2\r\n\r\n\r\n");
+        assert_eq!(scanner.next(), Some(2));
+        scanner.seek(6);
+        assert_eq!(scanner.next(), Some(6));
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal::<usize>(b"0"), Ok(0));
+        assert_eq!(parse_decimal::<usize>(b"1024"), Ok(1024));
+        assert_eq!(parse_decimal::<isize>(b"-1"), Ok(-1));
+        assert_eq!(parse_decimal::<i64>(b"+7"), Ok(7));
+        assert_eq!(parse_decimal::<i64>(b"-9223372036854775808"), Ok(i64::MIN));
+        assert_eq!(parse_decimal::<i64>(b"9223372036854775807"), Ok(i64::MAX));
+
+        assert!(parse_decimal::<usize>(b"").is_err());
+        assert!(parse_decimal::<usize>(b"-1").is_err());
+        assert!(parse_decimal::<usize>(b"1x").is_err());
+        assert!(parse_decimal::<i64>(b"9223372036854775808").is_err());
+    }
+}
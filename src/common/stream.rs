@@ -0,0 +1,386 @@
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Write};
+
+use lexical::{parse, ToLexical, BUFFER_SIZE};
+use memchr::memchr;
+
+use super::Error;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+/// Longest header line accepted before the CRLF, which is far more than any
+/// length prefix needs.
+const MAX_HEADER_LEN: usize = 32;
+
+/// Writes `prefix`, `len` and CRLF in a single call.
+fn write_header<W>(writer: &mut W, prefix: u8, len: usize) -> IoResult<()>
+where
+    W: Write,
+{
+    let mut header = [0u8; BUFFER_SIZE + 3];
+    header[0] = prefix;
+    let digits = len.to_lexical(&mut header[1..]).len();
+    header[1 + digits..3 + digits].copy_from_slice(b"\r\n");
+    writer.write_all(&header[..3 + digits])
+}
+
+/// Writes a bulk string of a known length whose payload is supplied in
+/// pieces, so the value never has to be held in memory at once.
+///
+/// Every write is checked against the declared length, and [`finish`]
+/// fails unless exactly that many bytes were written, as a short or long
+/// payload would desynchronize the connection.
+///
+/// [`finish`]: BulkWriter::finish
+#[derive(Debug)]
+pub struct BulkWriter<W> {
+    writer: W,
+    remaining: usize,
+}
+
+impl<W> BulkWriter<W>
+where
+    W: Write,
+{
+    /// Writes the `$<len>` header.
+    pub fn new(mut writer: W, len: usize) -> IoResult<Self> {
+        write_header(&mut writer, b'$', len)?;
+        Ok(Self {
+            writer,
+            remaining: len,
+        })
+    }
+
+    /// Bytes still owed to the declared length.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Copies the rest of the payload from `reader`, which must supply at
+    /// least that many bytes.
+    pub fn copy_from<R>(&mut self, reader: R) -> IoResult<u64>
+    where
+        R: Read,
+    {
+        let expected = self.remaining as u64;
+        let copied = std::io::copy(&mut reader.take(expected), self)?;
+        if copied < expected {
+            return Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                "reader ended before the declared bulk length",
+            ));
+        }
+        Ok(copied)
+    }
+
+    /// Writes the trailing CRLF and returns the inner writer.
+    pub fn finish(mut self) -> IoResult<W> {
+        if self.remaining != 0 {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "bulk payload is shorter than the declared length",
+            ));
+        }
+        self.writer.write_all(b"\r\n")?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Write for BulkWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        if buf.len() > self.remaining {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                "bulk payload is longer than the declared length",
+            ));
+        }
+        let written = self.writer.write(buf)?;
+        self.remaining -= written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes a RESP3 streamed string, `$?` followed by `;<len>` chunks and a
+/// final `;0`, for payloads whose length is not known up front. Each write
+/// becomes one chunk.
+///
+/// Only RESP3 peers understand this form.
+#[derive(Debug)]
+pub struct StreamedStringWriter<W> {
+    writer: W,
+}
+
+impl<W> StreamedStringWriter<W>
+where
+    W: Write,
+{
+    /// Writes the `$?` header.
+    pub fn new(mut writer: W) -> IoResult<Self> {
+        writer.write_all(b"$?\r\n")?;
+        Ok(Self { writer })
+    }
+
+    /// Writes the terminating empty chunk and returns the inner writer.
+    pub fn finish(mut self) -> IoResult<W> {
+        self.writer.write_all(b";0\r\n")?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Write for StreamedStringWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        // An empty chunk would end the string.
+        if !buf.is_empty() {
+            write_header(&mut self.writer, b';', buf.len())?;
+            self.writer.write_all(buf)?;
+            self.writer.write_all(b"\r\n")?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes a bulk string of `len` bytes read from `reader`.
+pub fn write_bulk_from_reader<W, R>(writer: &mut W, len: usize, reader: R) -> IoResult<()>
+where
+    W: Write,
+    R: Read,
+{
+    let mut bulk = BulkWriter::new(writer, len)?;
+    bulk.copy_from(reader)?;
+    bulk.finish()?;
+    Ok(())
+}
+
+/// Writes a bulk string of `len` bytes made of `chunks`.
+pub fn write_bulk_from_chunks<W, I>(writer: &mut W, len: usize, chunks: I) -> IoResult<()>
+where
+    W: Write,
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut bulk = BulkWriter::new(writer, len)?;
+    for chunk in chunks {
+        bulk.write_all(chunk.as_ref())?;
+    }
+    bulk.finish()?;
+    Ok(())
+}
+
+/// Writes everything `reader` yields as a RESP3 streamed string.
+pub fn write_streamed_string<W, R>(writer: &mut W, mut reader: R) -> IoResult<()>
+where
+    W: Write,
+    R: Read,
+{
+    let mut streamed = StreamedStringWriter::new(writer)?;
+    let mut chunk = vec![0u8; CHUNK_SIZE];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => streamed.write_all(&chunk[..read])?,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    streamed.finish()?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BulkHeader {
+    /// `$-1` or the RESP3 `_`.
+    Null,
+    Len(usize),
+    /// A RESP3 `$?` string of unknown length.
+    Streamed,
+    /// An error reply, `-` or the RESP3 `!`, whose message of the given
+    /// length follows in chunks like a payload.
+    Error(usize),
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BulkEvent<'a> {
+    Header(BulkHeader),
+    /// The next piece of the payload, borrowed from the input.
+    Chunk(&'a [u8]),
+    End,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum State {
+    Header,
+    Payload { remaining: usize, streamed: bool },
+    Trailer { streamed: bool },
+    ChunkHeader,
+    End,
+    Done,
+}
+
+/// Decodes a single bulk string reply incrementally, handing out the payload
+/// as it arrives instead of waiting for all of it, so memory stays bounded
+/// however large the value is.
+///
+/// Feed it input with [`decode`] until it yields [`BulkEvent::End`]. Every
+/// reply is reported as a header, zero or more chunks and the end, for
+/// nulls and error replies as well, so the stream stays in sync either way.
+///
+/// [`decode`]: BulkDecoder::decode
+#[derive(Debug, Clone)]
+pub struct BulkDecoder {
+    state: State,
+}
+
+impl Default for BulkDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BulkDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: State::Header,
+        }
+    }
+
+    /// True once [`BulkEvent::End`] has been returned.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Returns the next event and the number of bytes of `input` it used, or
+    /// `None` when `input` does not hold enough bytes for one, in which case
+    /// nothing was consumed and the same bytes must be passed again.
+    pub fn decode<'a>(&mut self, input: &'a [u8]) -> Result<Option<(BulkEvent<'a>, usize)>, Error> {
+        // Trailers and chunk headers produce no event of their own, so they
+        // are only committed together with the event that follows them.
+        let mut state = self.state;
+        let mut offset = 0;
+        let event = loop {
+            let input = &input[offset..];
+            match state {
+                State::Header if input.first() == Some(&b'-') => {
+                    // The message of a simple error is read like a payload
+                    // ending at the CRLF, so it is not bound by the header
+                    // length limit.
+                    let Some(end) = memchr(b'\r', input) else {
+                        return Ok(None);
+                    };
+                    state = State::Payload {
+                        remaining: end - 1,
+                        streamed: false,
+                    };
+                    offset += 1;
+                    break BulkEvent::Header(BulkHeader::Error(end - 1));
+                }
+                State::Header => {
+                    let Some((line, used)) = header_line(input)? else {
+                        return Ok(None);
+                    };
+                    let header = match line {
+                        b"_" | b"$-1" => BulkHeader::Null,
+                        b"$?" => BulkHeader::Streamed,
+                        [b'$', len @ ..] => BulkHeader::Len(parse(len)?),
+                        [b'!', len @ ..] => BulkHeader::Error(parse(len)?),
+                        _ => return Err(Error::InvalidBulkString),
+                    };
+                    state = match header {
+                        BulkHeader::Null => State::End,
+                        BulkHeader::Len(remaining) | BulkHeader::Error(remaining) => {
+                            State::Payload {
+                                remaining,
+                                streamed: false,
+                            }
+                        }
+                        BulkHeader::Streamed => State::ChunkHeader,
+                    };
+                    offset += used;
+                    break BulkEvent::Header(header);
+                }
+                State::ChunkHeader => {
+                    let Some((line, used)) = header_line(input)? else {
+                        return Ok(None);
+                    };
+                    let remaining = match line {
+                        [b';', len @ ..] => parse(len)?,
+                        _ => return Err(Error::InvalidBulkString),
+                    };
+                    offset += used;
+                    state = if remaining == 0 {
+                        State::End
+                    } else {
+                        State::Payload {
+                            remaining,
+                            streamed: true,
+                        }
+                    };
+                }
+                State::Payload {
+                    remaining: 0,
+                    streamed,
+                } => state = State::Trailer { streamed },
+                State::Payload {
+                    remaining,
+                    streamed,
+                } => {
+                    if input.is_empty() {
+                        return Ok(None);
+                    }
+                    let len = remaining.min(input.len());
+                    state = State::Payload {
+                        remaining: remaining - len,
+                        streamed,
+                    };
+                    offset += len;
+                    break BulkEvent::Chunk(&input[..len]);
+                }
+                State::Trailer { streamed } => {
+                    match input.get(..2) {
+                        Some(b"\r\n") => offset += 2,
+                        Some(_) => return Err(Error::InvalidBulkString),
+                        None => return Ok(None),
+                    }
+                    state = if streamed {
+                        State::ChunkHeader
+                    } else {
+                        State::End
+                    };
+                }
+                State::End => {
+                    state = State::Done;
+                    break BulkEvent::End;
+                }
+                State::Done => return Ok(None),
+            }
+        };
+
+        self.state = state;
+        Ok(Some((event, offset)))
+    }
+}
+
+/// Splits off a header line, without its CRLF, and the bytes it took up.
+fn header_line(input: &[u8]) -> Result<Option<(&[u8], usize)>, Error> {
+    match memchr(b'\r', input) {
+        Some(end) if end > MAX_HEADER_LEN => Err(Error::InvalidBulkString),
+        Some(end) => match input.get(end + 1) {
+            Some(b'\n') => Ok(Some((&input[..end], end + 2))),
+            Some(_) => Err(Error::InvalidBulkString),
+            None => Ok(None),
+        },
+        None if input.len() > MAX_HEADER_LEN => Err(Error::InvalidBulkString),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult, Write},
+    ops::Range,
+};
+
+const DEFAULT_PAYLOAD_THRESHOLD: usize = 1024;
+
+#[derive(Debug, Clone)]
+enum Segment<'a> {
+    Scratch(Range<usize>),
+    Payload(&'a [u8]),
+}
+
+/// Scatter/gather encoding target. Headers and small payloads are copied into
+/// a scratch buffer, while payloads of at least the payload threshold are
+/// kept as slices of the frame's input, so a multi-megabyte bulk string is
+/// never copied before it reaches the socket.
+#[derive(Debug, Clone)]
+pub struct VectoredEncoder<'a> {
+    scratch: Vec<u8>,
+    segments: Vec<Segment<'a>>,
+    payload_threshold: usize,
+}
+
+impl<'a> Default for VectoredEncoder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> VectoredEncoder<'a> {
+    pub fn new() -> Self {
+        Self::with_payload_threshold(DEFAULT_PAYLOAD_THRESHOLD)
+    }
+
+    /// Payloads shorter than `threshold` are copied into the scratch buffer,
+    /// as a separate segment would cost more than the copy.
+    pub fn with_payload_threshold(threshold: usize) -> Self {
+        Self {
+            scratch: Vec::new(),
+            segments: Vec::new(),
+            payload_threshold: threshold,
+        }
+    }
+
+    /// Appends bytes to the scratch buffer.
+    pub fn extend(&mut self, bytes: &[u8]) {
+        let start = self.scratch.len();
+        self.scratch.extend_from_slice(bytes);
+        match self.segments.last_mut() {
+            Some(Segment::Scratch(range)) if range.end == start => range.end = self.scratch.len(),
+            _ => self
+                .segments
+                .push(Segment::Scratch(start..self.scratch.len())),
+        }
+    }
+
+    /// Appends a payload borrowed from the frame.
+    pub fn payload(&mut self, payload: &'a [u8]) {
+        if payload.len() < self.payload_threshold {
+            self.extend(payload);
+        } else {
+            self.segments.push(Segment::Payload(payload));
+        }
+    }
+
+    /// Total number of encoded bytes.
+    pub fn len(&self) -> usize {
+        self.segments().map(<[u8]>::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// The encoded bytes, in order, for submission APIs that take plain
+    /// buffers rather than `IoSlice`s.
+    pub fn segments(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.segments.iter().map(|segment| match segment {
+            Segment::Scratch(range) => &self.scratch[range.clone()],
+            Segment::Payload(payload) => *payload,
+        })
+    }
+
+    pub fn io_slices(&self) -> Vec<IoSlice<'_>> {
+        self.segments().map(IoSlice::new).collect()
+    }
+
+    /// Writes every segment with `write_vectored`, retrying short writes.
+    pub fn write_to<W>(&self, writer: &mut W) -> IoResult<()>
+    where
+        W: Write,
+    {
+        let mut slices = self.io_slices();
+        let mut slices = &mut slices[..];
+        while !slices.is_empty() {
+            match writer.write_vectored(slices) {
+                Ok(0) => {
+                    return Err(IoError::new(
+                        ErrorKind::WriteZero,
+                        "failed to write the encoded frames",
+                    ))
+                }
+                Ok(n) => IoSlice::advance_slices(&mut slices, n),
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops every segment but keeps the scratch allocation.
+    pub fn clear(&mut self) {
+        self.scratch.clear();
+        self.segments.clear();
+    }
+}
+
+/// Writes go to the scratch buffer, so the existing writer-based encoders
+/// can emit headers here.
+impl<'a> Write for VectoredEncoder<'a> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.extend(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,164 @@
+use std::io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult, Write};
+
+use super::EncodeWithWriter;
+
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+const DEFAULT_VECTORED_THRESHOLD: usize = 4 * 1024;
+
+/// Coalesces the many small writes frame encoding makes into one buffer, so
+/// pipelined frames reach the inner writer in as few calls as possible.
+///
+/// Writes of at least the vectored threshold, in practice large bulk
+/// payloads, are not copied; they are sent together with the buffered bytes
+/// in a single `write_vectored` call.
+///
+/// Unlike `BufWriter`, buffered data is not flushed on drop.
+#[derive(Debug)]
+pub struct FrameWriter<W> {
+    writer: W,
+    buf: Vec<u8>,
+    auto_flush: Option<usize>,
+    vectored_threshold: usize,
+}
+
+impl<W> FrameWriter<W>
+where
+    W: Write,
+{
+    pub fn new(writer: W) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, writer)
+    }
+
+    /// The buffer is flushed automatically once it holds `capacity` bytes.
+    pub fn with_capacity(capacity: usize, writer: W) -> Self {
+        Self {
+            writer,
+            buf: Vec::with_capacity(capacity),
+            auto_flush: Some(capacity),
+            vectored_threshold: DEFAULT_VECTORED_THRESHOLD,
+        }
+    }
+
+    /// Flushes automatically once `threshold` bytes are buffered, or only on
+    /// explicit calls to `flush` when `None`, e.g. while batching a pipeline.
+    pub fn set_auto_flush(&mut self, threshold: Option<usize>) {
+        self.auto_flush = threshold;
+    }
+
+    /// Writes of at least `threshold` bytes bypass the buffer.
+    pub fn set_vectored_threshold(&mut self, threshold: usize) {
+        self.vectored_threshold = threshold;
+    }
+
+    pub fn write_frame<E>(&mut self, frame: E::Frame<'_>) -> IoResult<()>
+    where
+        E: EncodeWithWriter,
+    {
+        E::encode_with_writer(frame, self)
+    }
+
+    /// Bytes buffered but not yet handed to the inner writer.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flushes the buffer and returns the inner writer.
+    pub fn into_inner(mut self) -> IoResult<W> {
+        self.flush_buf()?;
+        Ok(self.writer)
+    }
+
+    fn flush_buf(&mut self) -> IoResult<()> {
+        self.write_through(&[]).map(|_| ())
+    }
+
+    /// Writes the buffer followed by `data` to the inner writer, returning
+    /// how much of `data` was taken.
+    ///
+    /// An error only comes back when nothing was written. Once part of the
+    /// buffer went out, the rest of the buffer and `data` stay buffered for
+    /// the next flush; once part of `data` went out, that part is reported,
+    /// so that a retry does not send it twice.
+    fn write_through(&mut self, data: &[u8]) -> IoResult<usize> {
+        let buffered = self.buf.len();
+        let mut slices = [IoSlice::new(&self.buf), IoSlice::new(data)];
+        let len = if data.is_empty() { 1 } else { 2 };
+        let mut slices = &mut slices[..len];
+        IoSlice::advance_slices(&mut slices, 0);
+
+        let mut written = 0;
+        let result = loop {
+            if slices.is_empty() {
+                break Ok(());
+            }
+            match self.writer.write_vectored(slices) {
+                Ok(0) => {
+                    break Err(IoError::new(
+                        ErrorKind::WriteZero,
+                        "failed to write the buffered data",
+                    ))
+                }
+                Ok(n) => {
+                    written += n;
+                    IoSlice::advance_slices(&mut slices, n);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => break Err(e),
+            }
+        };
+
+        // Keep whatever part of the buffer was not written, so a failed write
+        // can be retried with `flush`.
+        self.buf.drain(..written.min(buffered));
+        match result {
+            Ok(()) => Ok(data.len()),
+            Err(_) if written > buffered => Ok(written - buffered),
+            Err(_) if written > 0 && !data.is_empty() => {
+                self.buf.extend_from_slice(data);
+                Ok(data.len())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<W> Write for FrameWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        if data.len() >= self.vectored_threshold {
+            return self.write_through(data);
+        }
+
+        self.buf.extend_from_slice(data);
+        let buffered = self.buf.len();
+        if self
+            .auto_flush
+            .is_some_and(|threshold| buffered >= threshold)
+        {
+            if let Err(e) = self.flush_buf() {
+                // Nothing went out, so take `data` back and report the error;
+                // otherwise what is left stays buffered for the next flush.
+                if self.buf.len() == buffered {
+                    self.buf.truncate(buffered - data.len());
+                    return Err(e);
+                }
+            }
+        }
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.flush_buf()?;
+        self.writer.flush()
+    }
+}
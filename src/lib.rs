@@ -1,9 +1,20 @@
+pub mod cluster;
+pub mod command;
 mod common;
 mod decode;
 mod encode;
+#[cfg(feature = "test-util")]
+pub mod mock;
 pub mod v2;
 pub mod v3;
+pub mod visit;
 
-pub use common::{EncodeLen, EncodeWithWriter, Encoder, Error, ParseIter, Parser, Remaining};
+pub use common::{
+    write_bulk_from_chunks, write_bulk_from_reader, write_streamed_string, BulkDecoder, BulkEvent,
+    BulkHeader, BulkWriter, EncodeLen, EncodeToBuf, EncodeVectored, EncodeWithWriter, Encoder,
+    Error, FrameLen, FrameReader, FrameScan, FrameWriter, Invalidation, Message, ParseIter, Parser,
+    Redirect, RedisError, RedisErrorKind, Remaining, StreamedStringWriter, VectoredEncoder,
+    MAX_DEPTH,
+};
 pub use decode::decode;
 pub use encode::encode;
@@ -0,0 +1,320 @@
+//! An in-process Redis stand-in for testing client code without a live
+//! server. Requests are decoded with [`V2`] or [`V3`] and answered from a
+//! script of expected commands and canned replies.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{
+    v2::{Frame as V2Frame, V2},
+    v3::{Frame as V3Frame, V3},
+    Error, Parser,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Protocol {
+    V2,
+    V3,
+}
+
+/// The encoded bytes of one scripted reply.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reply(Vec<u8>);
+
+impl Reply {
+    pub fn v2(frame: &V2Frame<'_>) -> Self {
+        Self(frame.encode())
+    }
+
+    pub fn v3(frame: &V3Frame<'_>) -> Self {
+        Self(frame.encode())
+    }
+
+    /// Raw bytes, sent as they are, for malformed or hand-written replies.
+    pub fn raw(bytes: impl Into<Vec<u8>>) -> Self {
+        Self(bytes.into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The arguments of a decoded request and the bytes it took up.
+type Request = (Vec<Vec<u8>>, usize);
+
+#[derive(Debug)]
+struct Exchange {
+    request: Vec<Vec<u8>>,
+    replies: Vec<Reply>,
+}
+
+#[derive(Debug)]
+pub struct MockServer {
+    protocol: Protocol,
+    greeting: Vec<Reply>,
+    script: VecDeque<Exchange>,
+    chunk_size: Option<usize>,
+    failures: Vec<String>,
+}
+
+impl MockServer {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            greeting: Vec::new(),
+            script: VecDeque::new(),
+            chunk_size: None,
+            failures: Vec::new(),
+        }
+    }
+
+    /// Expects `request` next and answers it with `reply`.
+    pub fn expect(mut self, request: &[&[u8]], reply: Reply) -> Self {
+        self.script.push_back(Exchange {
+            request: request.iter().map(|arg| arg.to_vec()).collect(),
+            replies: vec![reply],
+        });
+        self
+    }
+
+    /// Sends an unsolicited frame right after the reply of the last expected
+    /// command, or as soon as a client connects if nothing is expected yet.
+    pub fn push(mut self, push: Reply) -> Self {
+        match self.script.back_mut() {
+            Some(exchange) => exchange.replies.push(push),
+            None => self.greeting.push(push),
+        }
+        self
+    }
+
+    /// Delivers replies in pieces of at most `chunk_size` bytes, so the client
+    /// decoder sees frames split at arbitrary positions.
+    pub fn partial_writes(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// True once every expected command was received and no unexpected one
+    /// was.
+    pub fn is_done(&self) -> bool {
+        self.script.is_empty() && self.failures.is_empty()
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        if let Some(failure) = self.failures.first() {
+            return Err(io::Error::other(failure.clone()));
+        }
+        match self.script.front() {
+            Some(exchange) => Err(io::Error::other(format!(
+                "expected command {} was never received",
+                display_args(&exchange.request)
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn greet(&mut self, output: &mut Vec<u8>) {
+        for reply in self.greeting.drain(..) {
+            output.extend_from_slice(reply.as_bytes());
+        }
+    }
+
+    fn decode_request(&self, input: &[u8]) -> Result<Option<Request>, Error> {
+        let request = match self.protocol {
+            Protocol::V2 => V2::parse(input)?.map(|(frame, consumed)| {
+                let args = match frame {
+                    V2Frame::Array(data) => data
+                        .iter()
+                        .map(|arg| arg.as_bytes().map(<[u8]>::to_vec))
+                        .collect::<Option<Vec<_>>>(),
+                    _ => None,
+                };
+                (args, consumed)
+            }),
+            Protocol::V3 => V3::parse(input)?.map(|(frame, consumed)| {
+                let args = match frame {
+                    V3Frame::Array { data, .. } => data
+                        .iter()
+                        .map(|arg| arg.as_bytes().map(<[u8]>::to_vec))
+                        .collect::<Option<Vec<_>>>(),
+                    _ => None,
+                };
+                (args, consumed)
+            }),
+        };
+
+        match request {
+            Some((Some(args), consumed)) => Ok(Some((args, consumed))),
+            Some((None, _)) => Err(Error::InvalidCommand),
+            None => Ok(None),
+        }
+    }
+
+    /// Answers every complete request in `input`, appending the replies to
+    /// `output`, and returns how many bytes were consumed.
+    pub fn handle(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<usize> {
+        let mut consumed = 0;
+        loop {
+            let request = match self.decode_request(&input[consumed..]) {
+                Ok(Some(request)) => request,
+                Ok(None) | Err(Error::NotComplete) => return Ok(consumed),
+                Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+            };
+            let (args, len) = request;
+            consumed += len;
+
+            match self.script.front() {
+                Some(exchange) if exchange.request == args => {
+                    let exchange = self.script.pop_front().expect("checked above");
+                    for reply in exchange.replies {
+                        output.extend_from_slice(reply.as_bytes());
+                    }
+                }
+                expected => {
+                    let failure = match expected {
+                        Some(exchange) => format!(
+                            "expected command {}, received {}",
+                            display_args(&exchange.request),
+                            display_args(&args)
+                        ),
+                        None => format!("unexpected command {}", display_args(&args)),
+                    };
+                    output.extend_from_slice(b"-ERR mock: ");
+                    output.extend_from_slice(failure.as_bytes());
+                    output.extend_from_slice(b"\r\n");
+                    self.failures.push(failure);
+                }
+            }
+        }
+    }
+
+    /// Serves the script over an in-memory stream.
+    pub fn duplex(mut self) -> MockStream {
+        let mut outbound = Vec::new();
+        self.greet(&mut outbound);
+        MockStream {
+            server: self,
+            inbound: Vec::new(),
+            outbound: VecDeque::from(outbound),
+        }
+    }
+
+    /// Serves the script to a single client on a loopback TCP port.
+    pub fn spawn(mut self) -> io::Result<MockHandle> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let thread = thread::spawn(move || {
+            let (stream, _) = listener.accept()?;
+            self.serve(stream)
+        });
+        Ok(MockHandle { addr, thread })
+    }
+
+    fn serve(&mut self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_nodelay(true)?;
+        let mut inbound = Vec::new();
+        let mut output = Vec::new();
+        let mut buf = [0u8; 4096];
+
+        self.greet(&mut output);
+        self.send(&mut stream, &mut output)?;
+        loop {
+            let read = stream.read(&mut buf)?;
+            if read == 0 {
+                return self.finish();
+            }
+            inbound.extend_from_slice(&buf[..read]);
+            let consumed = self.handle(&inbound, &mut output)?;
+            inbound.drain(..consumed);
+            self.send(&mut stream, &mut output)?;
+        }
+    }
+
+    fn send(&self, stream: &mut TcpStream, output: &mut Vec<u8>) -> io::Result<()> {
+        match self.chunk_size {
+            Some(chunk_size) => {
+                for chunk in output.chunks(chunk_size) {
+                    stream.write_all(chunk)?;
+                    stream.flush()?;
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+            None => stream.write_all(output)?,
+        }
+        output.clear();
+        Ok(())
+    }
+}
+
+fn display_args(args: &[Vec<u8>]) -> String {
+    args.iter()
+        .map(|arg| String::from_utf8_lossy(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A handle to a server started with [`MockServer::spawn`].
+pub struct MockHandle {
+    addr: SocketAddr,
+    thread: JoinHandle<io::Result<()>>,
+}
+
+impl MockHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Waits for the client to disconnect and checks the whole script ran.
+    pub fn join(self) -> io::Result<()> {
+        self.thread
+            .join()
+            .map_err(|_| io::Error::other("mock server panicked"))?
+    }
+}
+
+/// An in-memory connection to a [`MockServer`]. Writes are decoded as
+/// requests, and replies become readable immediately.
+#[derive(Debug)]
+pub struct MockStream {
+    server: MockServer,
+    inbound: Vec<u8>,
+    outbound: VecDeque<u8>,
+}
+
+impl MockStream {
+    /// Checks the whole script ran.
+    pub fn finish(self) -> io::Result<()> {
+        self.server.finish()
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = match self.server.chunk_size {
+            Some(chunk_size) => buf.len().min(chunk_size),
+            None => buf.len(),
+        };
+        self.outbound.read(&mut buf[..len])
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inbound.extend_from_slice(buf);
+        let mut output = Vec::new();
+        let consumed = self.server.handle(&self.inbound, &mut output)?;
+        self.inbound.drain(..consumed);
+        self.outbound.extend(output);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use std::{fmt, ops::Range};
+
+use minivec::MiniVec;
+
+use crate::common::{Error, MAX_DEPTH};
+
+use super::{tag::Tag, Frame, Lexer};
+
+#[derive(Debug, Clone)]
+enum Node {
+    BulkString(Range<usize>),
+    Null,
+    Integer(i64),
+    Array(Range<usize>),
+    SimpleError(Range<usize>),
+    SimpleString(Range<usize>),
+}
+
+/// Reusable storage for decoding a reply without a vector per array.
+///
+/// Every frame of a reply lives in one buffer, with the elements of each
+/// array stored next to each other, so decoding a nested reply costs a
+/// single allocation that later replies reuse.
+#[derive(Debug, Default)]
+pub struct DecodeArena {
+    nodes: Vec<Node>,
+}
+
+impl DecodeArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of frames the arena can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Decodes the first frame in `input`, replacing whatever the arena held.
+    /// Returns `None` if the frame is not complete yet, otherwise the frame
+    /// and the number of bytes it took.
+    pub fn decode<'r, 'a>(
+        &'r mut self,
+        input: &'a [u8],
+    ) -> Result<Option<(FrameRef<'r, 'a>, usize)>, Error> {
+        self.nodes.clear();
+        self.nodes.push(Node::Null);
+
+        let mut lexer = Lexer::new(input);
+        // Slots still to fill, as `next..end` ranges of `nodes`.
+        let mut stack: Vec<Range<usize>> = Vec::new();
+        stack.push(0..1);
+        while let Some(slots) = stack.last_mut() {
+            let Some(slot) = slots.next() else {
+                stack.pop();
+                continue;
+            };
+            let node = match lexer.next() {
+                Some(Ok(tag)) => match tag {
+                    Tag::BulkString(buf) => Node::BulkString(span(input, buf)),
+                    Tag::Null => Node::Null,
+                    Tag::Integer(number) => Node::Integer(number),
+                    Tag::SimpleError(buf) => Node::SimpleError(span(input, buf)),
+                    Tag::SimpleString(buf) => Node::SimpleString(span(input, buf)),
+                    Tag::Array(len) => {
+                        if len > lexer.max_frames() {
+                            return Ok(None);
+                        }
+                        // Below the root slot, every range on the stack is
+                        // an array enclosing this one.
+                        if stack.len() > MAX_DEPTH {
+                            return Err(Error::TooDeep);
+                        }
+                        let start = self.nodes.len();
+                        self.nodes.resize(start + len, Node::Null);
+                        stack.push(start..start + len);
+                        Node::Array(start..start + len)
+                    }
+                },
+                Some(Err(Error::NotComplete)) | None => return Ok(None),
+                Some(Err(e)) => return Err(e),
+            };
+            self.nodes[slot] = node;
+        }
+
+        let frame = FrameRef::new(input, &self.nodes, &self.nodes[0]);
+        Ok(Some((frame, lexer.remaining())))
+    }
+}
+
+fn span(input: &[u8], buf: &[u8]) -> Range<usize> {
+    let start = buf.as_ptr() as usize - input.as_ptr() as usize;
+    start..start + buf.len()
+}
+
+/// A frame decoded into a [`DecodeArena`], borrowing its payloads from the
+/// input and its elements from the arena.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameRef<'r, 'a> {
+    BulkString(&'a [u8]),
+    Null,
+    Integer(i64),
+    Array(Elements<'r, 'a>),
+    SimpleError(&'a [u8]),
+    SimpleString(&'a [u8]),
+}
+
+impl<'r, 'a> FrameRef<'r, 'a> {
+    fn new(input: &'a [u8], nodes: &'r [Node], node: &Node) -> Self {
+        match node {
+            Node::BulkString(data) => FrameRef::BulkString(&input[data.clone()]),
+            Node::Null => FrameRef::Null,
+            Node::Integer(number) => FrameRef::Integer(*number),
+            Node::Array(elements) => FrameRef::Array(Elements {
+                input,
+                nodes,
+                elements: &nodes[elements.clone()],
+            }),
+            Node::SimpleError(data) => FrameRef::SimpleError(&input[data.clone()]),
+            Node::SimpleString(data) => FrameRef::SimpleString(&input[data.clone()]),
+        }
+    }
+
+    /// Copies the frame out of the arena.
+    pub fn to_frame(&self) -> Frame<'a> {
+        match self {
+            FrameRef::BulkString(data) => Frame::BulkString(data),
+            FrameRef::Null => Frame::Null,
+            FrameRef::Integer(number) => Frame::Integer(*number),
+            FrameRef::Array(elements) => Frame::Array(
+                elements
+                    .iter()
+                    .map(|element| element.to_frame())
+                    .collect::<MiniVec<_>>(),
+            ),
+            FrameRef::SimpleError(data) => Frame::SimpleError(data),
+            FrameRef::SimpleString(data) => Frame::SimpleString(data),
+        }
+    }
+}
+
+/// The elements of an array decoded into a [`DecodeArena`].
+#[derive(Clone, Copy)]
+pub struct Elements<'r, 'a> {
+    input: &'a [u8],
+    nodes: &'r [Node],
+    elements: &'r [Node],
+}
+
+impl<'r, 'a> Elements<'r, 'a> {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<FrameRef<'r, 'a>> {
+        self.elements
+            .get(index)
+            .map(|node| FrameRef::new(self.input, self.nodes, node))
+    }
+
+    pub fn iter(&self) -> ElementsIter<'r, 'a> {
+        ElementsIter {
+            input: self.input,
+            nodes: self.nodes,
+            elements: self.elements.iter(),
+        }
+    }
+}
+
+impl PartialEq for Elements<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl fmt::Debug for Elements<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'r, 'a> IntoIterator for Elements<'r, 'a> {
+    type Item = FrameRef<'r, 'a>;
+    type IntoIter = ElementsIter<'r, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct ElementsIter<'r, 'a> {
+    input: &'a [u8],
+    nodes: &'r [Node],
+    elements: std::slice::Iter<'r, Node>,
+}
+
+impl<'r, 'a> Iterator for ElementsIter<'r, 'a> {
+    type Item = FrameRef<'r, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements
+            .next()
+            .map(|node| FrameRef::new(self.input, self.nodes, node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ElementsIter<'_, '_> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arena() {
+        let mut arena = DecodeArena::new();
+        let input = b"*3\r\n*2\r\n:1\r\n$-1\r\n$5\r\nhello\r\n*0\r\n+next\r\n";
+        let (frame, consumed) = arena.decode(input).unwrap().unwrap();
+        assert_eq!(consumed, input.len() - 7);
+
+        let FrameRef::Array(elements) = frame else {
+            panic!("expected an array");
+        };
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements.get(1), Some(FrameRef::BulkString(b"hello")));
+        assert!(matches!(elements.get(2), Some(FrameRef::Array(empty)) if empty.is_empty()));
+        assert_eq!(
+            frame.to_frame(),
+            crate::decode::<super::super::V2>(input).unwrap().unwrap().0
+        );
+
+        let (frame, _) = arena.decode(b":7\r\n").unwrap().unwrap();
+        assert_eq!(frame, FrameRef::Integer(7));
+    }
+
+    #[test]
+    fn test_arena_incomplete() {
+        let mut arena = DecodeArena::new();
+        assert_eq!(arena.decode(b"").unwrap(), None);
+        assert_eq!(arena.decode(b"*2\r\n:1\r\n").unwrap(), None);
+        assert_eq!(arena.decode(b"*1000000000\r\n").unwrap(), None);
+        assert!(arena.capacity() < 1000);
+        assert_eq!(arena.decode(b"*1\r\n?\r\n"), Err(Error::Unknown));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::common::Error;
+use crate::common::{Error, MAX_DEPTH};
 
 use super::{frame::Frame, tag::Tag, Lexer};
 use minivec::MiniVec;
@@ -36,56 +36,87 @@ impl<'a> Ast<'a> {
     }
 
     fn array_frame(&mut self, len: usize) -> Result<MiniVec<Frame<'a>>, Error> {
-        let data = MiniVec::with_capacity(len);
-        let mut stack = Vec::new();
-        stack.push((data, len));
+        array_frame(
+            &mut self.lexer,
+            len,
+            &mut Vec::new(),
+            MiniVec::with_capacity,
+        )
+    }
+}
 
-        while let Some((mut current_vec, mut current_len)) = stack.pop() {
-            if current_len == 0 {
-                if stack.is_empty() {
-                    return Ok(current_vec);
-                } else if let Some((parent_vec, parent_len)) = stack.last_mut() {
-                    parent_vec.push(Frame::Array(current_vec));
-                    *parent_len -= 1;
-                    continue;
-                }
+/// Builds an array of `len` elements from the tags that follow its header,
+/// walking nested arrays with `stack` instead of recursion. Every vector,
+/// including the one returned, comes from `alloc`, and on error the arrays
+/// still open are left on `stack`. Room is only reserved for as many
+/// elements as the rest of the input can hold, and nesting deeper than
+/// [`MAX_DEPTH`] is an error.
+pub(crate) fn array_frame<'a, F>(
+    lexer: &mut Lexer<'a>,
+    len: usize,
+    stack: &mut Vec<(MiniVec<Frame<'a>>, usize)>,
+    mut alloc: F,
+) -> Result<MiniVec<Frame<'a>>, Error>
+where
+    F: FnMut(usize) -> MiniVec<Frame<'a>>,
+{
+    let data = alloc(len.min(lexer.max_frames()));
+    stack.push((data, len));
+
+    while let Some((mut current_vec, mut current_len)) = stack.pop() {
+        if current_len == 0 {
+            if stack.is_empty() {
+                return Ok(current_vec);
+            } else if let Some((parent_vec, parent_len)) = stack.last_mut() {
+                parent_vec.push(Frame::Array(current_vec));
+                *parent_len -= 1;
+                continue;
             }
+        }
 
-            match self.lexer.next() {
-                Some(Ok(Tag::SimpleString(buf))) => {
-                    current_vec.push(Frame::SimpleString(buf));
-                    current_len -= 1;
-                }
-                Some(Ok(Tag::SimpleError(buf))) => {
-                    current_vec.push(Frame::SimpleError(buf));
-                    current_len -= 1;
-                }
-                Some(Ok(Tag::Integer(number))) => {
-                    current_vec.push(Frame::Integer(number));
-                    current_len -= 1;
-                }
-                Some(Ok(Tag::BulkString(buf))) => {
-                    current_vec.push(Frame::BulkString(buf));
-                    current_len -= 1;
-                }
-                Some(Ok(Tag::Null)) => {
-                    current_vec.push(Frame::Null);
-                    current_len -= 1;
-                }
-                Some(Ok(Tag::Array(len))) => {
-                    stack.push((current_vec, current_len));
-                    let new_vec = MiniVec::with_capacity(len);
-                    stack.push((new_vec, len));
-                    continue;
+        match lexer.next() {
+            Some(Ok(Tag::SimpleString(buf))) => {
+                current_vec.push(Frame::SimpleString(buf));
+                current_len -= 1;
+            }
+            Some(Ok(Tag::SimpleError(buf))) => {
+                current_vec.push(Frame::SimpleError(buf));
+                current_len -= 1;
+            }
+            Some(Ok(Tag::Integer(number))) => {
+                current_vec.push(Frame::Integer(number));
+                current_len -= 1;
+            }
+            Some(Ok(Tag::BulkString(buf))) => {
+                current_vec.push(Frame::BulkString(buf));
+                current_len -= 1;
+            }
+            Some(Ok(Tag::Null)) => {
+                current_vec.push(Frame::Null);
+                current_len -= 1;
+            }
+            Some(Ok(Tag::Array(len))) => {
+                stack.push((current_vec, current_len));
+                if stack.len() >= MAX_DEPTH {
+                    return Err(Error::TooDeep);
                 }
-                Some(Err(e)) => return Err(e),
-                None => return Err(Error::NotComplete),
+                let new_vec = alloc(len.min(lexer.max_frames()));
+                stack.push((new_vec, len));
+                continue;
+            }
+            Some(Err(e)) => {
+                stack.push((current_vec, current_len));
+                return Err(e);
+            }
+            None => {
+                stack.push((current_vec, current_len));
+                return Err(Error::NotComplete);
             }
-            stack.push((current_vec, current_len));
         }
-
-        Err(Error::NotComplete)
+        stack.push((current_vec, current_len));
     }
+
+    Err(Error::NotComplete)
 }
 
 impl<'a> Iterator for Ast<'a> {
@@ -0,0 +1,183 @@
+use std::mem;
+
+use minivec::MiniVec;
+
+use crate::common::Error;
+
+use super::{ast::array_frame, tag::Tag, Frame, Lexer};
+
+const DEFAULT_POOL_LIMIT: usize = 64;
+
+/// Decodes frames while keeping its working memory between calls.
+///
+/// The stack used to walk nested arrays is reused, and the vectors of frames
+/// handed back through [`Decoder::recycle`] are pooled and used for later
+/// arrays, so once warmed up a flat command decodes without allocating.
+#[derive(Debug)]
+pub struct Decoder {
+    stack: Vec<(MiniVec<Frame<'static>>, usize)>,
+    pool: Vec<MiniVec<Frame<'static>>>,
+    pool_limit: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self {
+            stack: Vec::new(),
+            pool: Vec::new(),
+            pool_limit: DEFAULT_POOL_LIMIT,
+        }
+    }
+
+    /// Keeps at most `limit` vectors in the pool, dropping the rest.
+    pub fn set_pool_limit(&mut self, limit: usize) {
+        self.pool_limit = limit;
+        self.pool.truncate(limit);
+    }
+
+    /// Number of vectors waiting to be reused.
+    pub fn pooled(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Decodes the first frame in `input`, like [`V2::parse`](super::V2).
+    pub fn decode<'a>(&mut self, input: &'a [u8]) -> Result<Option<(Frame<'a>, usize)>, Error> {
+        let mut lexer = Lexer::new(input);
+        let frame = match lexer.next() {
+            Some(Ok(tag)) => match tag {
+                Tag::BulkString(buf) => Ok(Frame::BulkString(buf)),
+                Tag::Null => Ok(Frame::Null),
+                Tag::Integer(number) => Ok(Frame::Integer(number)),
+                Tag::SimpleString(buf) => Ok(Frame::SimpleString(buf)),
+                Tag::SimpleError(buf) => Ok(Frame::SimpleError(buf)),
+                Tag::Array(len) => {
+                    let mut stack = relabel_stack(mem::take(&mut self.stack));
+                    let pool = &mut self.pool;
+                    let array = array_frame(&mut lexer, len, &mut stack, |len| match pool.pop() {
+                        Some(vec) => {
+                            let mut vec = relabel(vec);
+                            vec.reserve(len);
+                            vec
+                        }
+                        None => MiniVec::with_capacity(len),
+                    });
+                    // Arrays left open by an error go back to the pool.
+                    self.recycle_pending(&mut stack);
+                    self.stack = relabel_stack(stack);
+                    array.map(Frame::Array)
+                }
+            },
+            Some(Err(e)) => Err(e),
+            None => return Ok(None),
+        };
+        frame.map(|frame| Some((frame, lexer.remaining())))
+    }
+
+    /// Takes back the vectors of a decoded frame for reuse by later calls.
+    pub fn recycle(&mut self, frame: Frame<'_>) {
+        if let Frame::Array(data) = frame {
+            let mut pending = relabel_stack(mem::take(&mut self.stack));
+            pending.push((data, 0));
+            self.recycle_pending(&mut pending);
+            self.stack = relabel_stack(pending);
+        }
+    }
+
+    /// Empties the vectors in `pending`, nested ones included, into the pool.
+    fn recycle_pending(&mut self, pending: &mut Vec<(MiniVec<Frame<'_>>, usize)>) {
+        while let Some((mut vec, _)) = pending.pop() {
+            while let Some(frame) = vec.pop() {
+                if let Frame::Array(data) = frame {
+                    pending.push((data, 0));
+                }
+            }
+            if self.pool.len() < self.pool_limit && vec.capacity() > 0 {
+                self.pool.push(relabel(vec));
+            }
+        }
+    }
+}
+
+/// Reuses an empty vector for frames borrowing from a different input.
+fn relabel<'x, 'y>(mut vec: MiniVec<Frame<'x>>) -> MiniVec<Frame<'y>> {
+    vec.clear();
+    // SAFETY: the vector is empty, so it holds no borrow of either input, and
+    // `Frame<'x>` and `Frame<'y>` differ only in lifetime, so they share a
+    // layout.
+    unsafe { mem::transmute::<MiniVec<Frame<'x>>, MiniVec<Frame<'y>>>(vec) }
+}
+
+fn relabel_stack<'x, 'y>(
+    mut stack: Vec<(MiniVec<Frame<'x>>, usize)>,
+) -> Vec<(MiniVec<Frame<'y>>, usize)> {
+    stack.clear();
+    // SAFETY: as in `relabel`, the stack is empty and its element types
+    // differ only in lifetime.
+    unsafe {
+        mem::transmute::<Vec<(MiniVec<Frame<'x>>, usize)>, Vec<(MiniVec<Frame<'y>>, usize)>>(stack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{v2::V2, Parser};
+
+    #[test]
+    fn test_decoder() {
+        let mut decoder = Decoder::new();
+        let inputs: [&[u8]; 5] = [
+            b"+OK\r\n",
+            b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n",
+            b"*2\r\n*1\r\n:1\r\n*0\r\n:2\r\n",
+            b"*2\r\n$3\r\nGET\r\n",
+            b"",
+        ];
+        for input in inputs {
+            let expected = V2::parse(input);
+            let decoded = decoder.decode(input);
+            assert_eq!(decoded, expected);
+            if let Ok(Some((frame, _))) = decoded {
+                decoder.recycle(frame);
+            }
+        }
+        let pooled = decoder.pooled();
+        assert!(pooled >= 2);
+        assert_eq!(decoder.decode(b"*1\r\n?\r\n"), Err(Error::Unknown));
+        assert_eq!(decoder.pooled(), pooled);
+
+        decoder.set_pool_limit(1);
+        assert_eq!(decoder.pooled(), 1);
+
+        // A length the input cannot hold reserves nothing up front.
+        let input = b"*1152921504606846975\r\n:5\r\n";
+        assert_eq!(decoder.decode(input), Err(Error::NotComplete));
+        assert_eq!(V2::parse(input), Err(Error::NotComplete));
+    }
+
+    #[test]
+    fn test_decoder_reuses_vectors() {
+        let mut decoder = Decoder::new();
+        let input = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n";
+        let (frame, _) = decoder.decode(input).unwrap().unwrap();
+        let Frame::Array(data) = &frame else {
+            panic!("expected an array");
+        };
+        let first = data.as_ptr();
+        decoder.recycle(frame);
+
+        let other = input.to_vec();
+        let (frame, _) = decoder.decode(&other).unwrap().unwrap();
+        let Frame::Array(data) = &frame else {
+            panic!("expected an array");
+        };
+        assert_eq!(data.as_ptr(), first);
+        assert_eq!(decoder.pooled(), 0);
+    }
+}
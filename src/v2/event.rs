@@ -0,0 +1,145 @@
+use crate::{common::Error, Remaining};
+
+use super::{tag::Tag, Lexer};
+
+/// One step of a pull parse. Every `ArrayStart` is eventually matched by an
+/// `End`, even for empty arrays.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event<'a> {
+    SimpleString(&'a [u8]),
+    SimpleError(&'a [u8]),
+    Bulk(&'a [u8]),
+    Null,
+    Integer(i64),
+    ArrayStart(usize),
+    End,
+}
+
+/// Pull parser yielding [`Event`]s straight from the lexer, so callers can
+/// build their own structures, or count and skip elements, without the
+/// allocations of a full [`Frame`](super::Frame) tree.
+#[derive(Debug)]
+pub struct EventParser<'a> {
+    lexer: Lexer<'a>,
+    // Elements still expected by each open array.
+    stack: Vec<usize>,
+}
+
+impl<'a> EventParser<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            lexer: Lexer::new(input),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Number of arrays currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Skips the next value, subtree included, without reporting its events.
+    /// At the end of an array this consumes its `End` instead.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let depth = self.stack.len();
+        loop {
+            match self.next() {
+                Some(Ok(_)) if self.stack.len() <= depth => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::NotComplete),
+            }
+        }
+    }
+
+    fn complete_value(&mut self) {
+        if let Some(remaining) = self.stack.last_mut() {
+            *remaining -= 1;
+        }
+    }
+}
+
+impl<'a> Iterator for EventParser<'a> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stack.last() == Some(&0) {
+            self.stack.pop();
+            self.complete_value();
+            return Some(Ok(Event::End));
+        }
+
+        let tag = match self.lexer.next() {
+            Some(Ok(tag)) => tag,
+            Some(Err(e)) => return Some(Err(e)),
+            None if self.stack.is_empty() => return None,
+            None => return Some(Err(Error::NotComplete)),
+        };
+        let event = match tag {
+            Tag::Array(len) => {
+                self.stack.push(len);
+                return Some(Ok(Event::ArrayStart(len)));
+            }
+            Tag::SimpleString(buf) => Event::SimpleString(buf),
+            Tag::SimpleError(buf) => Event::SimpleError(buf),
+            Tag::BulkString(buf) => Event::Bulk(buf),
+            Tag::Null => Event::Null,
+            Tag::Integer(num) => Event::Integer(num),
+        };
+        self.complete_value();
+        Some(Ok(event))
+    }
+}
+
+impl<'a> Remaining for EventParser<'a> {
+    fn remaining(&self) -> usize {
+        self.lexer.remaining()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let input = b"*3\r\n$3\r\nfoo\r\n*0\r\n*2\r\n:1\r\n$-1\r\n+OK\r\n";
+        let events = EventParser::new(input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::ArrayStart(3),
+                Event::Bulk(b"foo"),
+                Event::ArrayStart(0),
+                Event::End,
+                Event::ArrayStart(2),
+                Event::Integer(1),
+                Event::Null,
+                Event::End,
+                Event::End,
+                Event::SimpleString(b"OK"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip() {
+        let input = b"*3\r\n*2\r\n:1\r\n:2\r\n-ERR\r\n$1\r\na\r\n:3\r\n";
+        let mut parser = EventParser::new(input);
+        assert_eq!(parser.next(), Some(Ok(Event::ArrayStart(3))));
+        parser.skip_value().unwrap();
+        assert_eq!(parser.depth(), 1);
+        parser.skip_value().unwrap();
+        assert_eq!(parser.next(), Some(Ok(Event::Bulk(b"a"))));
+        parser.skip_value().unwrap();
+        assert_eq!(parser.depth(), 0);
+        assert_eq!(parser.next(), Some(Ok(Event::Integer(3))));
+        assert_eq!(parser.remaining(), input.len());
+        assert_eq!(parser.next(), None);
+
+        let mut parser = EventParser::new(b"*2\r\n:1\r\n");
+        assert_eq!(parser.skip_value(), Err(Error::NotComplete));
+    }
+}
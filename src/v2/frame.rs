@@ -3,9 +3,13 @@ use std::{
     io::{Result as IoResult, Write},
 };
 
+use bytes::BufMut;
 use lexical::to_string;
 
-use crate::{common::Error, EncodeLen};
+use crate::{
+    common::{int_len, put_number, uint_len, Error, INVALIDATE_CHANNEL},
+    EncodeLen, Invalidation, RedisError, VectoredEncoder,
+};
 
 use super::utils::CRLF;
 use crate::v3::Frame as V3Frame;
@@ -22,62 +26,133 @@ pub enum Frame<'a> {
     SimpleString(&'a [u8]),
 }
 
+/// Kinds of pub/sub message carrying a payload after their channel.
+const MESSAGE_KINDS: [&[u8]; 2] = [b"message", b"smessage"];
+
+/// Kinds of pub/sub confirmation carrying a subscription count after their
+/// channel.
+const SUBSCRIPTION_KINDS: [&[u8]; 6] = [
+    b"subscribe",
+    b"psubscribe",
+    b"ssubscribe",
+    b"unsubscribe",
+    b"punsubscribe",
+    b"sunsubscribe",
+];
+
 impl<'a> Frame<'a> {
-    pub fn encode(&self) -> Vec<u8> {
+    /// Returns the payload of a simple or bulk string frame.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
         match self {
-            Self::Null => {
-                let mut buf = Vec::with_capacity(5);
-                buf.push(b'$');
-                buf.extend_from_slice(b"-1");
-                buf.extend_from_slice(&CRLF);
-                buf
+            Self::SimpleString(data) | Self::BulkString(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_error(&self) -> Option<RedisError<'a>> {
+        match self {
+            Self::SimpleError(data) => Some(RedisError::parse(data)),
+            _ => None,
+        }
+    }
+
+    /// RESP2 has no push type, so a pub/sub message array is the closest
+    /// thing to an out of band frame. Only the exact shapes the server sends
+    /// count, so that an ordinary reply whose first element happens to be
+    /// `message` is not taken for one:
+    ///
+    /// - `message` or `smessage`, a bulk string channel and the payload.
+    /// - `pmessage`, a bulk string pattern and channel, and the payload.
+    /// - A (un)subscribe confirmation, its channel and the subscription
+    ///   count. The channel is null when unsubscribing from nothing.
+    pub fn is_push(&self) -> bool {
+        let Self::Array(data) = self else {
+            return false;
+        };
+        match &data[..] {
+            [Self::BulkString(kind), Self::BulkString(_), _] if MESSAGE_KINDS.contains(kind) => {
+                true
+            }
+            [Self::BulkString(kind), Self::BulkString(_) | Self::Null, Self::Integer(_)] => {
+                SUBSCRIPTION_KINDS.contains(kind)
             }
+            [Self::BulkString(kind), Self::BulkString(_), Self::BulkString(_), _] => {
+                *kind == b"pmessage"
+            }
+            _ => false,
+        }
+    }
+
+    /// Decodes a message published on the `__redis__:invalidate` channel,
+    /// where a null key list means the whole keyspace was flushed.
+    pub fn as_invalidation(&self) -> Option<Invalidation<'a>> {
+        let Self::Array(data) = self else {
+            return None;
+        };
+        match &data[..] {
+            [kind, channel, keys]
+                if kind.as_bytes() == Some(b"message")
+                    && channel.as_bytes() == Some(INVALIDATE_CHANNEL) =>
+            {
+                match keys {
+                    Self::Null => Some(Invalidation::Flush),
+                    Self::Array(data) => data
+                        .iter()
+                        .map(|key| key.as_bytes())
+                        .collect::<Option<Vec<_>>>()
+                        .map(Invalidation::Keys),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Frame<'a> {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.encode_len());
+        self.encode_to_buf(&mut buf);
+        buf
+    }
+
+    /// Encodes into `buf` without allocating; reserve `encode_len()` bytes
+    /// beforehand to avoid growing it more than once.
+    pub fn encode_to_buf<B>(&self, buf: &mut B)
+    where
+        B: BufMut,
+    {
+        match self {
+            Self::Null => buf.put_slice(b"$-1\r\n"),
             Self::Integer(num) => {
-                let num_str = to_string(*num);
-                let num_str_len = num_str.as_bytes().len();
-                let mut buf = Vec::with_capacity(3 + num_str_len);
-                buf.push(b':');
-                buf.extend_from_slice(num_str.as_bytes());
-                buf.extend_from_slice(&CRLF);
-                buf
+                buf.put_u8(b':');
+                put_number(buf, *num);
+                buf.put_slice(&CRLF);
             }
             Self::SimpleError(text) => {
-                let mut buf = Vec::with_capacity(3 + text.len());
-                buf.push(b'-');
-                buf.extend_from_slice(text);
-                buf.extend_from_slice(&CRLF);
-                buf
+                buf.put_u8(b'-');
+                buf.put_slice(text);
+                buf.put_slice(&CRLF);
             }
             Self::SimpleString(text) => {
-                let mut buf = Vec::with_capacity(3 + text.len());
-                buf.push(b'+');
-                buf.extend_from_slice(text);
-                buf.extend_from_slice(&CRLF);
-                buf
+                buf.put_u8(b'+');
+                buf.put_slice(text);
+                buf.put_slice(&CRLF);
             }
             Self::BulkString(text) => {
-                let text_len = text.len();
-                let text_len_str = to_string(text_len);
-                let mut buf = Vec::with_capacity(5 + text_len + text_len_str.len());
-                buf.push(b'$');
-                buf.extend_from_slice(text_len_str.as_bytes());
-                buf.extend_from_slice(&CRLF);
-                buf.extend_from_slice(text);
-                buf.extend_from_slice(&CRLF);
-                buf
+                buf.put_u8(b'$');
+                put_number(buf, text.len());
+                buf.put_slice(&CRLF);
+                buf.put_slice(text);
+                buf.put_slice(&CRLF);
             }
             Self::Array(array) => {
-                let array_len = array.len();
-                let array_len_str = to_string(array_len);
-                let mut buf = Vec::with_capacity(3 + array_len_str.len());
-                buf.push(b'*');
-                buf.extend_from_slice(array_len_str.as_bytes());
-                buf.extend_from_slice(&CRLF);
+                buf.put_u8(b'*');
+                put_number(buf, array.len());
+                buf.put_slice(&CRLF);
                 for frame in array {
-                    let tmp = frame.encode();
-                    buf.extend(tmp.into_iter());
+                    frame.encode_to_buf(buf);
                 }
-                buf
             }
         }
     }
@@ -129,23 +204,59 @@ impl<'a> Frame<'a> {
 
         Ok(())
     }
+
+    /// Encodes into `encoder`, referencing bulk payloads instead of copying
+    /// them.
+    pub fn encode_vectored(&self, encoder: &mut VectoredEncoder<'a>) {
+        match self {
+            Self::Null => encoder.extend(b"$-1\r\n"),
+            Self::Integer(num) => {
+                let num_str = to_string(*num);
+                encoder.extend(b":");
+                encoder.extend(num_str.as_bytes());
+                encoder.extend(b"\r\n");
+            }
+            Self::Array(array) => {
+                let array_len_str = to_string(array.len());
+                encoder.extend(b"*");
+                encoder.extend(array_len_str.as_bytes());
+                encoder.extend(b"\r\n");
+                for frame in array {
+                    frame.encode_vectored(encoder);
+                }
+            }
+            Self::BulkString(text) => {
+                let text_len_str = to_string(text.len());
+                encoder.extend(b"$");
+                encoder.extend(text_len_str.as_bytes());
+                encoder.extend(b"\r\n");
+                encoder.payload(text);
+                encoder.extend(b"\r\n");
+            }
+            Self::SimpleString(text) => {
+                encoder.extend(b"+");
+                encoder.extend(text);
+                encoder.extend(b"\r\n");
+            }
+            Self::SimpleError(text) => {
+                encoder.extend(b"-");
+                encoder.extend(text);
+                encoder.extend(b"\r\n");
+            }
+        }
+    }
 }
 
 impl<'a> EncodeLen for Frame<'a> {
     fn encode_len(&self) -> usize {
         match self {
             Self::Null => 5,
-            Self::Integer(num) => {
-                let num_str = to_string(*num);
-                3 + num_str.len()
-            }
+            Self::Integer(num) => 3 + int_len(*num),
             Self::SimpleString(text) => 3 + text.len(),
             Self::SimpleError(err) => 3 + err.len(),
-            Self::BulkString(text) => 3 + text.len() + 2,
+            Self::BulkString(text) => 5 + uint_len(text.len()) + text.len(),
             Self::Array(array) => {
-                let array_len = array.len();
-                let array_len_str = to_string(array_len);
-                3 + array_len_str.len() + array.iter().map(|f| f.encode_len()).sum::<usize>()
+                3 + uint_len(array.len()) + array.iter().map(|f| f.encode_len()).sum::<usize>()
             }
         }
     }
@@ -156,7 +267,7 @@ impl<'a> TryFrom<V3Frame<'a>> for Frame<'a> {
 
     fn try_from(value: V3Frame<'a>) -> Result<Self, Self::Error> {
         match value {
-            V3Frame::Null { data: _ } => Ok(Self::Null),
+            V3Frame::Null { .. } => Ok(Self::Null),
             V3Frame::Integer { data, attributes } => Ok(Self::Integer(data as i64)),
             V3Frame::SimpleString { data, attributes } => Ok(Self::SimpleString(data)),
             V3Frame::SimpleError { data, attributes } => Ok(Self::SimpleError(data)),
@@ -172,7 +283,7 @@ impl<'a> TryFrom<V3Frame<'a>> for Frame<'a> {
 
                 while let Some((mut current_vec, mut current_queue)) = stack.pop() {
                     match current_queue.pop_front() {
-                        Some(V3Frame::Null { data: _ }) => {
+                        Some(V3Frame::Null { .. }) => {
                             current_vec.push(Frame::Null);
                             stack.push((current_vec, current_queue));
                         }
@@ -301,7 +412,10 @@ mod test {
                         data: b"str",
                         attributes: None,
                     },
-                    V3Frame::Null { data: () },
+                    V3Frame::Null {
+                        data: (),
+                        attributes: None,
+                    },
                     V3Frame::SimpleError {
                         data: b"err",
                         attributes: None
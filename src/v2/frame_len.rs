@@ -0,0 +1,114 @@
+use crate::common::{line, parse_decimal, skip_payload, Error, FrameScan, MAX_DEPTH};
+
+/// Walks the frame header by header, keeping only a count of the values
+/// still owed by each open array, on a stack no deeper than [`MAX_DEPTH`], so
+/// nothing is allocated.
+pub(crate) fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+    resume(&mut FrameScan::new(), input)
+}
+
+/// Carries on measuring from `scan`, which only moves past a line once the
+/// line and its payload have fully arrived.
+pub(crate) fn resume(scan: &mut FrameScan, input: &[u8]) -> Result<Option<usize>, Error> {
+    loop {
+        let (first, follow, mut next) = match line(input, scan.position) {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+
+        match first {
+            b'+' | b'-' => {}
+            b':' => {
+                parse_decimal::<i64>(follow)?;
+            }
+            b'$' => match parse_decimal::<isize>(follow)? {
+                -1 => {}
+                len if len < 0 => return Err(Error::InvalidBulkString),
+                len => match skip_payload(input, next, len as usize, Error::InvalidBulkString)? {
+                    Some(end) => next = end,
+                    None => return Ok(None),
+                },
+            },
+            // The lexer reads no null array, so neither does this.
+            b'*' => {
+                let len = parse_decimal::<usize>(follow)?;
+                if scan.depth >= MAX_DEPTH {
+                    return Err(Error::TooDeep);
+                }
+                scan.position = next;
+                if len > 0 {
+                    scan.depth += 1;
+                    scan.levels[scan.depth] = (first, len);
+                    continue;
+                }
+            }
+            _ => return Err(Error::Unknown),
+        }
+        scan.position = next;
+
+        // Settle the value just read, closing every array it completes.
+        loop {
+            let owed = &mut scan.levels[scan.depth].1;
+            *owed -= 1;
+            if *owed > 0 {
+                break;
+            }
+            if scan.depth == 0 {
+                return Ok(Some(scan.position));
+            }
+            scan.depth -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_len() {
+        assert_eq!(frame_len(b"+OK\r\n:1\r\n"), Ok(Some(5)));
+        assert_eq!(frame_len(b"$12\r\nhello\r\nworld\r\n"), Ok(Some(19)));
+        assert_eq!(frame_len(b"*2\r\n$-1\r\n*0\r\n+next\r\n"), Ok(Some(13)));
+
+        assert_eq!(frame_len(b""), Ok(None));
+        assert_eq!(frame_len(b"*2\r\n:1\r\n"), Ok(None));
+        assert_eq!(frame_len(b"$5\r\nhel"), Ok(None));
+        assert_eq!(frame_len(b"$5\r\nhello\r"), Ok(None));
+
+        assert_eq!(
+            frame_len(b"$5\r\nhello!\r\n"),
+            Err(Error::InvalidBulkString)
+        );
+        assert!(frame_len(b"*-1\r\n").is_err());
+        assert!(frame_len(b"*-2\r\n").is_err());
+        assert_eq!(frame_len(b"?\r\n"), Err(Error::Unknown));
+        assert_eq!(frame_len(b"\r\n"), Err(Error::Unknown));
+        assert!(frame_len(b":x\r\n").is_err());
+        assert_eq!(
+            frame_len(b"*9223372036854775807\r\n*9223372036854775807\r\n*9223372036854775807\r\n"),
+            Ok(None)
+        );
+
+        let mut input = b"*1\r\n".repeat(MAX_DEPTH);
+        input.extend_from_slice(b"*0\r\n");
+        assert_eq!(frame_len(&input), Err(Error::TooDeep));
+        assert_eq!(frame_len(&input[4..]), Ok(Some(MAX_DEPTH * 4)));
+    }
+
+    #[test]
+    fn test_resume() {
+        let input = b"*3\r\n$5\r\nhello\r\n*2\r\n:1\r\n$-1\r\n+OK\r\n";
+        let mut scan = FrameScan::new();
+        for end in 0..input.len() {
+            assert_eq!(resume(&mut scan, &input[..end]), Ok(None));
+        }
+        assert_eq!(resume(&mut scan, input), Ok(Some(input.len())));
+
+        scan.reset();
+        assert_eq!(resume(&mut scan, b"*2\r\n:1"), Ok(None));
+        assert!(resume(&mut scan, b"*2\r\n:x\r\n").is_err());
+        scan.reset();
+        assert_eq!(resume(&mut scan, b"+OK\r\n"), Ok(Some(5)));
+    }
+}
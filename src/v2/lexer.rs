@@ -1,33 +1,25 @@
-use crate::common::Error;
-use lexical::{format::STANDARD, parse_with_options, FromLexicalWithOptions, ParseIntegerOptions};
-use memchr::{
-    memmem::{FindIter, Finder, FinderBuilder, Prefilter},
-    Memchr,
-};
+use crate::common::{parse_decimal, skip_payload, CrlfScanner, Error};
 
-use super::{ast::Ast, tag::Tag, utils::CRLF};
+use super::tag::Tag;
 
 type ScanResult<T> = Result<T, Error>;
 
+/// Smallest encoding of a frame (`+\r\n`), used to reject lengths that cannot
+/// fit in the input before reserving room for them.
+const MIN_FRAME_LEN: usize = 3;
+
 #[derive(Debug)]
 pub(crate) struct Lexer<'a> {
     input: &'a [u8],
-    // scanner: FindIter<'a, 'static>,
-    scanner: Memchr<'a>,
+    scanner: CrlfScanner<'a>,
     last_position: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub(crate) fn new(input: &'a [u8]) -> Self {
-        // let mut builder = FinderBuilder::new();
-        // builder.prefilter(Prefilter::Auto);
-        // let finder = builder.build_forward(&CRLF);
-        // let scanner = finder.find_iter(input).into_owned();
-        let scanner = Memchr::new(b'\r', input);
-
         Self {
             input,
-            scanner,
+            scanner: CrlfScanner::new(input),
             last_position: 0,
         }
     }
@@ -36,12 +28,23 @@ impl<'a> Lexer<'a> {
         self.last_position
     }
 
+    /// Most frames the rest of the input can hold, an upper bound on what an
+    /// array header can honestly declare.
+    pub(crate) fn max_frames(&self) -> usize {
+        (self.input.len() - self.last_position) / MIN_FRAME_LEN
+    }
+
+    /// Takes the payload by its declared length, so it may contain CRLF.
     fn scan_bulk_string(&mut self, len: usize) -> Option<ScanResult<Tag<'a>>> {
-        let content = self.walk()?;
-        if content.len() != len {
-            Some(Err(Error::InvalidBulkString))
-        } else {
-            Some(Ok(Tag::BulkString(content)))
+        let start = self.last_position;
+        match skip_payload(self.input, start, len, Error::InvalidBulkString) {
+            Ok(Some(end)) => {
+                self.last_position = end;
+                self.scanner.seek(end);
+                Some(Ok(Tag::BulkString(&self.input[start..end - 2])))
+            }
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
         }
     }
 
@@ -50,46 +53,22 @@ impl<'a> Lexer<'a> {
         match first {
             b'+' => Some(Ok(Tag::SimpleString(follow))),
             b'-' => Some(Ok(Tag::SimpleError(follow))),
-            b'$' => {
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<isize, _, STANDARD>(follow, &options);
-                match len_result {
-                    Ok(-1) => Some(Ok(Tag::Null)),
-                    Ok(len) => self.scan_bulk_string(len as usize),
-                    Err(e) => Some(Err(Error::from(e))),
-                }
-            }
-            b':' => {
-                let options = ParseIntegerOptions::new();
-                let num_result = parse_with_options::<i64, _, STANDARD>(follow, &options);
-                Some(
-                    num_result
-                        .map_err(|e| Error::from(e))
-                        .map(|num| Tag::Integer(num)),
-                )
-            }
-            b'*' => {
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<usize, _, STANDARD>(follow, &options);
-                Some(
-                    len_result
-                        .map_err(|e| Error::from(e))
-                        .map(|len| Tag::Array(len)),
-                )
-            }
+            b'$' => match parse_decimal::<isize>(follow) {
+                Ok(-1) => Some(Ok(Tag::Null)),
+                Ok(len) if len < 0 => Some(Err(Error::InvalidBulkString)),
+                Ok(len) => self.scan_bulk_string(len as usize),
+                Err(e) => Some(Err(e)),
+            },
+            b':' => Some(parse_decimal::<i64>(follow).map(Tag::Integer)),
+            b'*' => Some(parse_decimal::<usize>(follow).map(Tag::Array)),
             _ => Some(Err(Error::Unknown)),
         }
     }
 
     fn walk(&mut self) -> Option<&'a [u8]> {
-        let split = loop {
-            let end_position = self.scanner.next()?;
-            if let Some(b'\n') = self.input.get(end_position + 1) {
-                let split = self.input.get(self.last_position..end_position)?;
-                self.last_position = end_position + 2; // +2 to skip the CRLF
-                break split;
-            }
-        };
+        let end_position = self.scanner.next()?;
+        let split = self.input.get(self.last_position..end_position)?;
+        self.last_position = end_position + 2; // +2 to skip the CRLF
         Some(split)
     }
 }
@@ -1,16 +1,26 @@
+mod arena;
 mod ast;
+mod decoder;
+mod event;
 mod frame;
+mod frame_len;
 mod lexer;
 mod tag;
 mod utils;
 
+pub use arena::{DecodeArena, Elements, ElementsIter, FrameRef};
 pub(super) use ast::Ast;
+pub use decoder::Decoder;
+pub use event::{Event, EventParser};
 pub use frame::Frame;
 pub(super) use lexer::Lexer;
 
+use std::ops::Range;
+
 use crate::{
     common::{Encoder, Parser},
-    EncodeWithWriter, Error, ParseIter, Remaining,
+    EncodeToBuf, EncodeVectored, EncodeWithWriter, Error, FrameLen, FrameScan, Message, ParseIter,
+    Remaining, VectoredEncoder,
 };
 
 pub struct V2;
@@ -33,6 +43,82 @@ impl Remaining for DecodeIter<'_> {
     }
 }
 
+impl<'a> DecodeIter<'a> {
+    /// Splits the stream into pub/sub messages and replies to requests.
+    pub fn messages(self) -> Messages<'a> {
+        Messages { inner: self }
+    }
+
+    /// Pairs every frame with the range of input it was decoded from, so it
+    /// can be forwarded or logged byte for byte.
+    pub fn spanned(self) -> Spanned<'a> {
+        Spanned { inner: self }
+    }
+}
+
+pub struct Messages<'a> {
+    inner: DecodeIter<'a>,
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<Message<Frame<'a>>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|result| {
+            result.map(|frame| {
+                if frame.is_push() {
+                    Message::Push(frame)
+                } else {
+                    Message::Reply(frame)
+                }
+            })
+        })
+    }
+}
+
+impl Remaining for Messages<'_> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+}
+
+pub struct Spanned<'a> {
+    inner: DecodeIter<'a>,
+}
+
+impl<'a> Iterator for Spanned<'a> {
+    type Item = Result<(Frame<'a>, Range<usize>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.inner.remaining();
+        let frame = self.inner.next()?;
+        Some(frame.map(|frame| (frame, start..self.inner.remaining())))
+    }
+}
+
+impl Remaining for Spanned<'_> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+}
+
+impl V2 {
+    /// Returns the encoded length of the first frame in `input` without
+    /// building it, or `None` if the frame is not complete yet.
+    pub fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::frame_len(input)
+    }
+}
+
+impl FrameLen for V2 {
+    fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::frame_len(input)
+    }
+    fn resume_frame_len(scan: &mut FrameScan, input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::resume(scan, input)
+    }
+}
+
 impl Parser for V2 {
     type Frame<'a> = (Frame<'a>, usize);
 
@@ -66,6 +152,25 @@ impl Encoder for V2 {
     }
 }
 
+impl EncodeToBuf for V2 {
+    type Frame<'a> = Frame<'a>;
+
+    fn encode_to_buf<B>(frame: Self::Frame<'_>, buf: &mut B)
+    where
+        B: bytes::BufMut,
+    {
+        frame.encode_to_buf(buf)
+    }
+}
+
+impl EncodeVectored for V2 {
+    type Frame<'a> = Frame<'a>;
+
+    fn encode_vectored<'a>(frame: Self::Frame<'a>, encoder: &mut VectoredEncoder<'a>) {
+        frame.encode_vectored(encoder)
+    }
+}
+
 impl EncodeWithWriter for V2 {
     type Frame<'a> = Frame<'a>;
 
@@ -0,0 +1,653 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Range,
+};
+
+use minivec::MiniVec;
+
+use crate::common::{parse_decimal, parse_double, Error, MAX_DEPTH};
+
+use super::{big_number::check_big_number, tag::TagType, Frame, Lexer};
+
+#[derive(Debug, Clone)]
+enum Value {
+    SimpleString(Range<usize>),
+    SimpleError(Range<usize>),
+    Boolean(bool),
+    Null,
+    Integer(isize),
+    Double(f64),
+    BulkString(Range<usize>),
+    BulkError(Range<usize>),
+    VerbatimString([u8; 3], Range<usize>),
+    Array(Range<usize>),
+    Map(Range<usize>),
+    Set(Range<usize>),
+    Push(Range<usize>),
+    BigNumber(Range<usize>),
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    value: Value,
+    /// Key and value slots of the attributes sent before the value.
+    attributes: Option<Range<usize>>,
+}
+
+const EMPTY: Node = Node {
+    value: Value::Null,
+    attributes: None,
+};
+
+/// Reusable storage for decoding a reply without a collection per aggregate.
+///
+/// Every frame of a reply lives in one buffer, with the elements of each
+/// aggregate stored next to each other, so decoding a nested reply costs a
+/// single allocation that later replies reuse. Maps and attributes store
+/// their entries as alternating keys and values.
+#[derive(Debug, Default)]
+pub struct DecodeArena {
+    nodes: Vec<Node>,
+}
+
+impl DecodeArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Number of frames the arena can hold before it has to grow.
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Decodes the first frame in `input`, replacing whatever the arena held.
+    /// Returns `None` if the frame is not complete yet, otherwise the frame
+    /// and the number of bytes it took.
+    pub fn decode<'r, 'a>(
+        &'r mut self,
+        input: &'a [u8],
+    ) -> Result<Option<(FrameRef<'r, 'a>, usize)>, Error> {
+        self.nodes.clear();
+        self.nodes.push(EMPTY);
+
+        let mut lexer = Lexer::new(input);
+        // Slots still to fill, as `next..end` ranges of `nodes`, with the
+        // number of aggregates around them.
+        let mut stack: Vec<(Range<usize>, usize)> = Vec::new();
+        stack.push((0..1, 0));
+        while let Some((slots, depth)) = stack.last_mut() {
+            let depth = *depth;
+            let Some(slot) = slots.next() else {
+                stack.pop();
+                continue;
+            };
+            let tag = match lexer.next() {
+                Some(Ok(tag)) => tag,
+                Some(Err(Error::NotComplete)) | None => return Ok(None),
+                Some(Err(e)) => return Err(e),
+            };
+            let data = tag.start_position..tag.end_position;
+
+            // Aggregates reserve one slot per element up front, so their
+            // elements end up next to each other.
+            let block = match tag.tag_type {
+                TagType::Array
+                | TagType::Set
+                | TagType::Push
+                | TagType::Map
+                | TagType::Attribute => {
+                    let len = parse_decimal::<usize>(&input[data.clone()])?;
+                    let len = match tag.tag_type {
+                        TagType::Map | TagType::Attribute => {
+                            len.checked_mul(2).ok_or(Error::InvalidMap)?
+                        }
+                        _ => len,
+                    };
+                    if len > lexer.max_frames() {
+                        return Ok(None);
+                    }
+                    if depth >= MAX_DEPTH {
+                        return Err(Error::TooDeep);
+                    }
+                    let start = self.nodes.len();
+                    self.nodes.resize(start + len, EMPTY);
+                    start..start + len
+                }
+                _ => 0..0,
+            };
+
+            let value = match tag.tag_type {
+                TagType::SimpleString => Value::SimpleString(data),
+                TagType::SimpleError => Value::SimpleError(data),
+                TagType::Boolean => match &input[data] {
+                    b"t" => Value::Boolean(true),
+                    b"f" => Value::Boolean(false),
+                    _ => return Err(Error::InvalidBoolean),
+                },
+                TagType::Null => Value::Null,
+                TagType::Integer => Value::Integer(parse_decimal::<isize>(&input[data])?),
+                TagType::Double => Value::Double(parse_double(&input[data])?),
+                TagType::BulkString => Value::BulkString(data),
+                TagType::BulkError => Value::BulkError(data),
+                TagType::VerbatimString => {
+                    let format = input
+                        .get(data.start..data.start + 3)
+                        .and_then(|format| format.try_into().ok())
+                        .ok_or(Error::InvalidVerbatimString)?;
+                    Value::VerbatimString(format, data.start + 4..data.end)
+                }
+                TagType::BigNumber => {
+                    check_big_number(&input[data.clone()])?;
+                    Value::BigNumber(data)
+                }
+                TagType::Array => Value::Array(block.clone()),
+                TagType::Set => Value::Set(block.clone()),
+                TagType::Push => Value::Push(block.clone()),
+                TagType::Map => Value::Map(block.clone()),
+                TagType::Attribute => {
+                    // The annotated value still belongs in `slot`, so fill it
+                    // again once the attribute entries are done.
+                    stack.push((slot..slot + 1, depth));
+                    stack.push((block.clone(), depth + 1));
+                    self.nodes[slot].attributes = Some(block);
+                    continue;
+                }
+            };
+            self.nodes[slot].value = value;
+            if !block.is_empty() {
+                stack.push((block, depth + 1));
+            }
+        }
+
+        let frame = FrameRef::new(input, &self.nodes, &self.nodes[0]);
+        Ok(Some((frame, lexer.remaining())))
+    }
+}
+
+/// A frame decoded into a [`DecodeArena`], borrowing its payloads from the
+/// input and its elements from the arena.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameRef<'r, 'a> {
+    SimpleString {
+        data: &'a [u8],
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    SimpleError {
+        data: &'a [u8],
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Boolean {
+        data: bool,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Null {
+        data: (),
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Integer {
+        data: isize,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Double {
+        data: f64,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    BulkString {
+        data: &'a [u8],
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    BulkError {
+        data: &'a [u8],
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    VerbatimString {
+        data: ([u8; 3], &'a [u8]),
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Array {
+        data: Elements<'r, 'a>,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Map {
+        data: Pairs<'r, 'a>,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Set {
+        data: Elements<'r, 'a>,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    Push {
+        data: Elements<'r, 'a>,
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+    BigNumber {
+        data: &'a [u8],
+        attributes: Option<Pairs<'r, 'a>>,
+    },
+}
+
+impl<'r, 'a> FrameRef<'r, 'a> {
+    fn new(input: &'a [u8], nodes: &'r [Node], node: &Node) -> Self {
+        let elements = |range: &Range<usize>| Elements {
+            input,
+            nodes,
+            elements: &nodes[range.clone()],
+        };
+        let pairs = |range: &Range<usize>| Pairs {
+            entries: elements(range),
+        };
+        let attributes = node.attributes.as_ref().map(pairs);
+        match &node.value {
+            Value::SimpleString(data) => FrameRef::SimpleString {
+                data: &input[data.clone()],
+                attributes,
+            },
+            Value::SimpleError(data) => FrameRef::SimpleError {
+                data: &input[data.clone()],
+                attributes,
+            },
+            Value::Boolean(data) => FrameRef::Boolean {
+                data: *data,
+                attributes,
+            },
+            Value::Null => FrameRef::Null {
+                data: (),
+                attributes,
+            },
+            Value::Integer(data) => FrameRef::Integer {
+                data: *data,
+                attributes,
+            },
+            Value::Double(data) => FrameRef::Double {
+                data: *data,
+                attributes,
+            },
+            Value::BulkString(data) => FrameRef::BulkString {
+                data: &input[data.clone()],
+                attributes,
+            },
+            Value::BulkError(data) => FrameRef::BulkError {
+                data: &input[data.clone()],
+                attributes,
+            },
+            Value::VerbatimString(format, data) => FrameRef::VerbatimString {
+                data: (*format, &input[data.clone()]),
+                attributes,
+            },
+            Value::Array(data) => FrameRef::Array {
+                data: elements(data),
+                attributes,
+            },
+            Value::Map(data) => FrameRef::Map {
+                data: pairs(data),
+                attributes,
+            },
+            Value::Set(data) => FrameRef::Set {
+                data: elements(data),
+                attributes,
+            },
+            Value::Push(data) => FrameRef::Push {
+                data: elements(data),
+                attributes,
+            },
+            Value::BigNumber(data) => FrameRef::BigNumber {
+                data: &input[data.clone()],
+                attributes,
+            },
+        }
+    }
+
+    /// Copies the frame out of the arena.
+    pub fn to_frame(&self) -> Frame<'a> {
+        let attributes = |pairs: &Option<Pairs<'r, 'a>>| pairs.map(|pairs| pairs.to_map());
+        match self {
+            FrameRef::SimpleString {
+                data,
+                attributes: a,
+            } => Frame::SimpleString {
+                data,
+                attributes: attributes(a),
+            },
+            FrameRef::SimpleError {
+                data,
+                attributes: a,
+            } => Frame::SimpleError {
+                data,
+                attributes: attributes(a),
+            },
+            FrameRef::Boolean {
+                data,
+                attributes: a,
+            } => Frame::Boolean {
+                data: *data,
+                attributes: attributes(a),
+            },
+            FrameRef::Null {
+                data,
+                attributes: a,
+            } => Frame::Null {
+                data: *data,
+                attributes: attributes(a),
+            },
+            FrameRef::Integer {
+                data,
+                attributes: a,
+            } => Frame::Integer {
+                data: *data,
+                attributes: attributes(a),
+            },
+            FrameRef::Double {
+                data,
+                attributes: a,
+            } => Frame::Double {
+                data: *data,
+                raw: None,
+                attributes: attributes(a),
+            },
+            FrameRef::BulkString {
+                data,
+                attributes: a,
+            } => Frame::BulkString {
+                data,
+                attributes: attributes(a),
+            },
+            FrameRef::BulkError {
+                data,
+                attributes: a,
+            } => Frame::BulkError {
+                data,
+                attributes: attributes(a),
+            },
+            FrameRef::VerbatimString {
+                data,
+                attributes: a,
+            } => Frame::VerbatimString {
+                data: *data,
+                attributes: attributes(a),
+            },
+            FrameRef::Array {
+                data,
+                attributes: a,
+            } => Frame::Array {
+                data: data
+                    .iter()
+                    .map(|frame| frame.to_frame())
+                    .collect::<MiniVec<_>>(),
+                attributes: attributes(a),
+            },
+            FrameRef::Map {
+                data,
+                attributes: a,
+            } => Frame::Map {
+                data: data.to_map(),
+                attributes: attributes(a),
+            },
+            FrameRef::Set {
+                data,
+                attributes: a,
+            } => Frame::Set {
+                data: data
+                    .iter()
+                    .map(|frame| frame.to_frame())
+                    .collect::<HashSet<_>>(),
+                attributes: attributes(a),
+            },
+            FrameRef::Push {
+                data,
+                attributes: a,
+            } => Frame::Push {
+                data: data
+                    .iter()
+                    .map(|frame| frame.to_frame())
+                    .collect::<MiniVec<_>>(),
+                attributes: attributes(a),
+            },
+            FrameRef::BigNumber {
+                data,
+                attributes: a,
+            } => Frame::BigNumber {
+                data,
+                attributes: attributes(a),
+            },
+        }
+    }
+}
+
+/// The elements of an aggregate decoded into a [`DecodeArena`].
+#[derive(Clone, Copy)]
+pub struct Elements<'r, 'a> {
+    input: &'a [u8],
+    nodes: &'r [Node],
+    elements: &'r [Node],
+}
+
+impl<'r, 'a> Elements<'r, 'a> {
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<FrameRef<'r, 'a>> {
+        self.elements
+            .get(index)
+            .map(|node| FrameRef::new(self.input, self.nodes, node))
+    }
+
+    pub fn iter(&self) -> ElementsIter<'r, 'a> {
+        ElementsIter {
+            input: self.input,
+            nodes: self.nodes,
+            elements: self.elements.iter(),
+        }
+    }
+}
+
+impl PartialEq for Elements<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl fmt::Debug for Elements<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'r, 'a> IntoIterator for Elements<'r, 'a> {
+    type Item = FrameRef<'r, 'a>;
+    type IntoIter = ElementsIter<'r, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct ElementsIter<'r, 'a> {
+    input: &'a [u8],
+    nodes: &'r [Node],
+    elements: std::slice::Iter<'r, Node>,
+}
+
+impl<'r, 'a> Iterator for ElementsIter<'r, 'a> {
+    type Item = FrameRef<'r, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements
+            .next()
+            .map(|node| FrameRef::new(self.input, self.nodes, node))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.elements.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ElementsIter<'_, '_> {}
+
+/// The entries of a map or attribute decoded into a [`DecodeArena`], in the
+/// order they were received.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Pairs<'r, 'a> {
+    entries: Elements<'r, 'a>,
+}
+
+impl<'r, 'a> Pairs<'r, 'a> {
+    pub fn len(&self) -> usize {
+        self.entries.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<(FrameRef<'r, 'a>, FrameRef<'r, 'a>)> {
+        Some((
+            self.entries.get(index * 2)?,
+            self.entries.get(index * 2 + 1)?,
+        ))
+    }
+
+    pub fn iter(&self) -> PairsIter<'r, 'a> {
+        PairsIter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    fn to_map(self) -> HashMap<Frame<'a>, Frame<'a>> {
+        self.iter()
+            .map(|(key, value)| (key.to_frame(), value.to_frame()))
+            .collect()
+    }
+}
+
+impl fmt::Debug for Pairs<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<'r, 'a> IntoIterator for Pairs<'r, 'a> {
+    type Item = (FrameRef<'r, 'a>, FrameRef<'r, 'a>);
+    type IntoIter = PairsIter<'r, 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+pub struct PairsIter<'r, 'a> {
+    entries: ElementsIter<'r, 'a>,
+}
+
+impl<'r, 'a> Iterator for PairsIter<'r, 'a> {
+    type Item = (FrameRef<'r, 'a>, FrameRef<'r, 'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.entries.next()?, self.entries.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.entries.len() / 2;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for PairsIter<'_, '_> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_arena() {
+        let mut arena = DecodeArena::new();
+        let input = b"*3\r\n%1\r\n+k\r\n*2\r\n:1\r\n:2\r\n|1\r\n+ttl\r\n:3\r\n#t\r\n~1\r\n,1.5\r\n+next\r\n";
+        let (frame, consumed) = arena.decode(input).unwrap().unwrap();
+        assert_eq!(consumed, input.len() - 7);
+
+        let FrameRef::Array { data, attributes } = frame else {
+            panic!("expected an array");
+        };
+        assert_eq!(attributes, None);
+        assert_eq!(data.len(), 3);
+
+        let Some(FrameRef::Map { data: map, .. }) = data.get(0) else {
+            panic!("expected a map");
+        };
+        let (key, value) = map.get(0).unwrap();
+        assert_eq!(
+            key,
+            FrameRef::SimpleString {
+                data: b"k",
+                attributes: None
+            }
+        );
+        assert!(matches!(value, FrameRef::Array { data, .. } if data.len() == 2));
+
+        let Some(FrameRef::Boolean {
+            data: true,
+            attributes: Some(attributes),
+        }) = data.get(1)
+        else {
+            panic!("expected an annotated boolean");
+        };
+        assert_eq!(attributes.len(), 1);
+        assert_eq!(
+            attributes.get(0).unwrap().1,
+            FrameRef::Integer {
+                data: 3,
+                attributes: None
+            }
+        );
+
+        let mut expected = HashMap::new();
+        expected.insert(
+            Frame::SimpleString {
+                data: b"ttl",
+                attributes: None,
+            },
+            Frame::Integer {
+                data: 3,
+                attributes: None,
+            },
+        );
+        assert_eq!(
+            data.get(1).unwrap().to_frame(),
+            Frame::Boolean {
+                data: true,
+                attributes: Some(expected),
+            }
+        );
+
+        let (frame, _) = arena.decode(b"=8\r\ntxt:a\r\nb\r\n").unwrap().unwrap();
+        assert!(matches!(
+            frame,
+            FrameRef::VerbatimString {
+                data: (format, b"a\r\nb"),
+                ..
+            } if &format == b"txt"
+        ));
+    }
+
+    #[test]
+    fn test_arena_incomplete() {
+        let mut arena = DecodeArena::new();
+        assert_eq!(arena.decode(b"").unwrap(), None);
+        assert_eq!(arena.decode(b"%1\r\n+k\r\n").unwrap(), None);
+        assert_eq!(arena.decode(b"|1\r\n+k\r\n:1\r\n").unwrap(), None);
+        assert_eq!(arena.decode(b"~1000000000\r\n").unwrap(), None);
+        assert!(arena.capacity() < 1000);
+        assert_eq!(arena.decode(b"*1\r\n#x\r\n"), Err(Error::InvalidBoolean));
+    }
+}
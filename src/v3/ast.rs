@@ -3,70 +3,379 @@ use std::{
     iter::Peekable,
 };
 
-use crate::common::Error;
-use lexical::{format::STANDARD, parse_with_options, ParseFloatOptions, ParseIntegerOptions};
+use crate::common::{parse_decimal, parse_double, Error, MAX_DEPTH};
 use minivec::MiniVec;
 
-use super::{frame::Frame, lexer::Lexer, tag::TagType};
+use super::{
+    big_number::check_big_number,
+    frame::Frame,
+    lexer::{Lexer, MIN_FRAME_LEN},
+    tag::{Tag, TagType},
+};
 
 #[derive(Debug)]
 pub(crate) struct Ast<'a> {
     input: &'a [u8],
     lexer: Peekable<Lexer<'a>>,
+    keep_double_text: bool,
+}
+
+/// An aggregate whose elements are still being read. Hash tables are boxed
+/// and map keys waiting for their value are kept on a separate stack, so
+/// that opening and closing an aggregate moves little.
+#[allow(clippy::box_collection)]
+#[derive(Debug)]
+enum Open<'a> {
+    Array {
+        data: MiniVec<Frame<'a>>,
+        len: usize,
+        attributes: Option<Box<HashMap<Frame<'a>, Frame<'a>>>>,
+    },
+    Map {
+        data: Box<HashMap<Frame<'a>, Frame<'a>>>,
+        len: usize,
+        keyed: bool,
+        attributes: Option<Box<HashMap<Frame<'a>, Frame<'a>>>>,
+    },
+    Set {
+        data: Box<HashSet<Frame<'a>>>,
+        len: usize,
+        attributes: Option<Box<HashMap<Frame<'a>, Frame<'a>>>>,
+    },
+    Push {
+        data: MiniVec<Frame<'a>>,
+        len: usize,
+        attributes: Option<Box<HashMap<Frame<'a>, Frame<'a>>>>,
+    },
+    Attribute {
+        data: Box<HashMap<Frame<'a>, Frame<'a>>>,
+        len: usize,
+        keyed: bool,
+    },
+}
+
+impl<'a> Open<'a> {
+    fn slot(&self) -> Slot {
+        match self {
+            Open::Map { keyed: false, .. } => Slot::MapKey,
+            Open::Set { .. } => Slot::SetMember,
+            Open::Attribute { .. } => Slot::Attribute,
+            _ => Slot::Value,
+        }
+    }
+
+    /// Adds `frame` and returns whether the aggregate is complete.
+    #[inline(always)]
+    fn push(&mut self, frame: Frame<'a>, keys: &mut Vec<Frame<'a>>) -> bool {
+        match self {
+            Open::Array { data, len, .. } | Open::Push { data, len, .. } => {
+                data.push(frame);
+                *len -= 1;
+            }
+            Open::Map {
+                data, len, keyed, ..
+            }
+            | Open::Attribute { data, len, keyed } => {
+                if *keyed {
+                    if let Some(key) = keys.pop() {
+                        data.insert(key, frame);
+                    }
+                    *len -= 1;
+                } else {
+                    keys.push(frame);
+                }
+                *keyed = !*keyed;
+            }
+            Open::Set { data, len, .. } => {
+                data.insert(frame);
+                *len -= 1;
+            }
+        }
+        self.is_done()
+    }
+
+    #[inline(always)]
+    fn is_done(&self) -> bool {
+        match self {
+            Open::Array { len, .. }
+            | Open::Map { len, .. }
+            | Open::Set { len, .. }
+            | Open::Push { len, .. }
+            | Open::Attribute { len, .. } => *len == 0,
+        }
+    }
+
+    /// Whether the next value completes the aggregate.
+    #[inline(always)]
+    fn is_last(&self) -> bool {
+        match self {
+            Open::Array { len, .. } | Open::Set { len, .. } | Open::Push { len, .. } => *len == 1,
+            Open::Map { len, keyed, .. } | Open::Attribute { len, keyed, .. } => {
+                *len == 1 && *keyed
+            }
+        }
+    }
+
+    fn into_frame(self) -> Frame<'a> {
+        match self {
+            Open::Array {
+                data, attributes, ..
+            } => Frame::Array {
+                data,
+                attributes: attributes.map(|attributes| *attributes),
+            },
+            Open::Map {
+                data, attributes, ..
+            } => Frame::Map {
+                data: *data,
+                attributes: attributes.map(|attributes| *attributes),
+            },
+            Open::Set {
+                data, attributes, ..
+            } => Frame::Set {
+                data: *data,
+                attributes: attributes.map(|attributes| *attributes),
+            },
+            Open::Push {
+                data, attributes, ..
+            } => Frame::Push {
+                data,
+                attributes: attributes.map(|attributes| *attributes),
+            },
+            Open::Attribute { .. } => unreachable!("attributes are not a frame"),
+        }
+    }
+}
+
+fn is_aggregate(tag_type: &TagType) -> bool {
+    matches!(
+        tag_type,
+        TagType::Array | TagType::Map | TagType::Set | TagType::Push | TagType::Attribute
+    )
+}
+
+/// Where the next value goes, which limits what it can be.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Slot {
+    Value,
+    MapKey,
+    SetMember,
+    Attribute,
+}
+
+impl Slot {
+    pub(super) fn check(self, tag_type: &TagType) -> Result<(), Error> {
+        match (self, tag_type) {
+            (Slot::MapKey, TagType::Map | TagType::Set | TagType::Push) => Err(Error::InvalidMap),
+            (Slot::SetMember, TagType::Map | TagType::Set | TagType::Push) => {
+                Err(Error::InvalidSet)
+            }
+            (Slot::Attribute, TagType::Map) => Err(Error::InvalidMap),
+            (Slot::Attribute, TagType::Set) => Err(Error::InvalidSet),
+            (Slot::Attribute, TagType::Push | TagType::Attribute) => Err(Error::Unknown),
+            _ => Ok(()),
+        }
+    }
 }
 
 impl<'a> Ast<'a> {
     pub(crate) fn new(input: &'a [u8]) -> Self {
         let lexer = Lexer::new(input);
         let peek = lexer.peekable();
-        Self { input, lexer: peek }
+        Self {
+            input,
+            lexer: peek,
+            keep_double_text: false,
+        }
+    }
+
+    /// Keeps the text of every double alongside its value.
+    pub(crate) fn keep_double_text(&mut self) {
+        self.keep_double_text = true;
     }
 
     fn next_frame(&mut self) -> Option<Result<Frame<'a>, Error>> {
-        match self.lexer.next() {
-            Some(Ok(tag)) => match tag.tag_type {
-                TagType::Boolean => {
-                    Some(self.parse_boolean(tag.start_position, tag.end_position, None))
-                }
-                TagType::SimpleString => {
-                    Some(self.parse_simple_string(tag.start_position, tag.end_position, None))
-                }
-                TagType::SimpleError => {
-                    Some(self.parse_simple_error(tag.start_position, tag.end_position, None))
-                }
-                TagType::Null => Some(Ok(Frame::Null { data: () })),
-                TagType::Integer => {
-                    Some(self.parse_integer(tag.start_position, tag.end_position, None))
-                }
-                TagType::Double => {
-                    Some(self.parse_double(tag.start_position, tag.end_position, None))
-                }
-                TagType::BulkString => {
-                    Some(self.parse_bulk_string(tag.start_position, tag.end_position, None))
+        self.build_frame().transpose()
+    }
+
+    /// Builds the next frame, keeping the aggregates still being read on an
+    /// explicit stack so that deep nesting cannot overflow the thread stack.
+    fn build_frame(&mut self) -> Result<Option<Frame<'a>>, Error> {
+        let mut stack = Vec::new();
+        let mut keys = Vec::new();
+        // Attributes read for the value that comes next.
+        let mut attributes = None;
+
+        loop {
+            let tag = match stack.last_mut() {
+                Some(Open::Array { data, len, .. }) if attributes.is_none() => {
+                    self.fill_array(data, len)?
                 }
-                TagType::BulkError => {
-                    Some(self.parse_bulk_error(tag.start_position, tag.end_position, None))
+                Some(open) if attributes.is_none() => self.fill(open, &mut keys)?,
+                _ => match self.lexer.next() {
+                    Some(Ok(tag)) => tag,
+                    Some(Err(err)) => return Err(err),
+                    None if stack.is_empty() && attributes.is_none() => return Ok(None),
+                    None => return Err(Error::NotComplete),
+                },
+            };
+            let mut frame = if is_aggregate(&tag.tag_type) {
+                match self.open(&tag, &mut stack, &mut attributes)? {
+                    Some(frame) => frame,
+                    None => continue,
                 }
-                TagType::VerbatimString => {
-                    Some(self.parse_verbatim_string(tag.start_position, tag.end_position, None))
+            } else {
+                self.scalar(&tag, attributes.take())?
+            };
+
+            // Hand the frame to its parent and close every aggregate it fills.
+            loop {
+                let Some(open) = stack.last_mut() else {
+                    return Ok(Some(frame));
+                };
+                if !open.push(frame, &mut keys) {
+                    break;
                 }
-                TagType::BigNumber => {
-                    Some(self.parse_big_number(tag.start_position, tag.end_position, None))
+                match stack.pop() {
+                    Some(Open::Attribute { data, .. }) => {
+                        attributes = Some(*data);
+                        break;
+                    }
+                    Some(open) => frame = open.into_frame(),
+                    None => break,
                 }
-                TagType::Array => {
-                    Some(self.parse_array(tag.start_position, tag.end_position, None))
+            }
+        }
+    }
+
+    /// Pushes scalar elements straight into an open array, which keeps the
+    /// common case out of the general loop. Returns the tag of the first
+    /// element that needs that loop, or of the last one so that the loop
+    /// closes the array.
+    #[inline(never)]
+    fn fill_array(&mut self, data: &mut MiniVec<Frame<'a>>, len: &mut usize) -> Result<Tag, Error> {
+        loop {
+            let tag = match self.lexer.next() {
+                Some(Ok(tag)) => tag,
+                Some(Err(err)) => return Err(err),
+                None => return Err(Error::NotComplete),
+            };
+            if is_aggregate(&tag.tag_type) || *len == 1 {
+                return Ok(tag);
+            }
+            data.push(self.scalar(&tag, None)?);
+            *len -= 1;
+        }
+    }
+
+    /// Like [`Ast::fill_array`], for the other aggregates.
+    #[inline(never)]
+    fn fill(&mut self, open: &mut Open<'a>, keys: &mut Vec<Frame<'a>>) -> Result<Tag, Error> {
+        loop {
+            let tag = match self.lexer.next() {
+                Some(Ok(tag)) => tag,
+                Some(Err(err)) => return Err(err),
+                None => return Err(Error::NotComplete),
+            };
+            if is_aggregate(&tag.tag_type) || open.is_last() {
+                return Ok(tag);
+            }
+            open.push(self.scalar(&tag, None)?, keys);
+        }
+    }
+
+    #[inline(always)]
+    fn scalar(
+        &self,
+        tag: &Tag,
+        attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
+    ) -> Result<Frame<'a>, Error> {
+        let (start, end) = (tag.start_position, tag.end_position);
+        match tag.tag_type {
+            TagType::Boolean => self.parse_boolean(start, end, attributes),
+            TagType::SimpleString => self.parse_simple_string(start, end, attributes),
+            TagType::SimpleError => self.parse_simple_error(start, end, attributes),
+            TagType::Null => Ok(Frame::Null {
+                data: (),
+                attributes,
+            }),
+            TagType::Integer => self.parse_integer(start, end, attributes),
+            TagType::Double => self.parse_double(start, end, attributes),
+            TagType::BulkString => self.parse_bulk_string(start, end, attributes),
+            TagType::BulkError => self.parse_bulk_error(start, end, attributes),
+            TagType::VerbatimString => self.parse_verbatim_string(start, end, attributes),
+            TagType::BigNumber => self.parse_big_number(start, end, attributes),
+            _ => Err(Error::Unknown),
+        }
+    }
+
+    /// Starts reading the aggregate announced by `tag`, checking that it may
+    /// fill the slot it is read for and is no deeper than [`MAX_DEPTH`]. An
+    /// empty aggregate is returned at once instead.
+    #[inline(never)]
+    fn open(
+        &self,
+        tag: &Tag,
+        stack: &mut Vec<Open<'a>>,
+        attributes: &mut Option<HashMap<Frame<'a>, Frame<'a>>>,
+    ) -> Result<Option<Frame<'a>>, Error> {
+        let slot = stack.last().map_or(Slot::Value, Open::slot);
+        slot.check(&tag.tag_type)?;
+        if stack.len() >= MAX_DEPTH {
+            return Err(Error::TooDeep);
+        }
+
+        let len = self.parse_len(tag.start_position, tag.end_position)?;
+        // Only reserve room for as many elements as the rest of the input
+        // can hold, whatever the header claims.
+        let capacity = len.min(self.input.len().saturating_sub(tag.end_position) / MIN_FRAME_LEN);
+        let open = match tag.tag_type {
+            TagType::Array => Open::Array {
+                data: MiniVec::with_capacity(capacity),
+                len,
+                attributes: attributes.take().map(Box::new),
+            },
+            TagType::Map => Open::Map {
+                data: Box::new(HashMap::with_capacity(capacity)),
+                len,
+                keyed: false,
+                attributes: attributes.take().map(Box::new),
+            },
+            TagType::Set => Open::Set {
+                data: Box::new(HashSet::with_capacity(capacity)),
+                len,
+                attributes: attributes.take().map(Box::new),
+            },
+            TagType::Push => Open::Push {
+                data: MiniVec::with_capacity(capacity),
+                len,
+                attributes: attributes.take().map(Box::new),
+            },
+            TagType::Attribute if attributes.is_none() => {
+                if len == 0 {
+                    *attributes = Some(HashMap::new());
+                    return Ok(None);
                 }
-                TagType::Map => Some(self.parse_map(tag.start_position, tag.end_position, None)),
-                TagType::Set => Some(self.parse_set(tag.start_position, tag.end_position, None)),
-                TagType::Push => Some(self.parse_push(tag.start_position, tag.end_position)),
-                TagType::Attribute => {
-                    Some(self.parse_attribute(tag.start_position, tag.end_position))
+                Open::Attribute {
+                    data: Box::new(HashMap::with_capacity(capacity)),
+                    len,
+                    keyed: false,
                 }
-                _ => Some(Err(Error::Unknown)),
-            },
-            Some(Err(err)) => Some(Err(err)),
-            None => None,
+            }
+            _ => return Err(Error::Unknown),
+        };
+        if len == 0 {
+            return Ok(Some(open.into_frame()));
         }
+        stack.push(open);
+        Ok(None)
+    }
+    fn parse_len(&self, start_position: usize, end_position: usize) -> Result<usize, Error> {
+        let len_bytes = self
+            .input
+            .get(start_position..end_position)
+            .ok_or(Error::NotComplete)?;
+        parse_decimal::<usize>(len_bytes)
     }
 
     #[inline(always)]
@@ -127,8 +436,7 @@ impl<'a> Ast<'a> {
     ) -> Result<Frame<'a>, Error> {
         match self.input.get(start_position..end_position) {
             Some(number_str) => {
-                let option = ParseIntegerOptions::new();
-                let number = parse_with_options::<isize, &[u8], STANDARD>(number_str, &option)?;
+                let number = parse_decimal::<isize>(number_str)?;
                 Ok(Frame::Integer {
                     data: number,
                     attributes,
@@ -146,14 +454,11 @@ impl<'a> Ast<'a> {
         attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
     ) -> Result<Frame<'a>, Error> {
         match self.input.get(start_position..end_position) {
-            Some(number_str) => {
-                let option = ParseFloatOptions::new();
-                let number = parse_with_options::<f64, &[u8], STANDARD>(number_str, &option)?;
-                Ok(Frame::Double {
-                    data: number,
-                    attributes,
-                })
-            }
+            Some(number_str) => Ok(Frame::Double {
+                data: parse_double(number_str)?,
+                raw: self.keep_double_text.then_some(number_str),
+                attributes,
+            }),
             None => Err(Error::NotComplete),
         }
     }
@@ -196,9 +501,10 @@ impl<'a> Ast<'a> {
             .get(start_position..start_position + 3)
             .ok_or(Error::NotComplete)?;
         let encode_type = encode_type.try_into().map_err(|_| Error::Unknown)?;
+        // Skip the `:` after the format, which the lexer has checked.
         let data = self
             .input
-            .get(start_position + 3..end_position)
+            .get(start_position + 4..end_position)
             .ok_or(Error::NotComplete)?;
         Ok(Frame::VerbatimString {
             data: (encode_type, data),
@@ -206,232 +512,6 @@ impl<'a> Ast<'a> {
         })
     }
 
-    fn parse_array(
-        &mut self,
-        start_position: usize,
-        end_position: usize,
-        attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
-    ) -> Result<Frame<'a>, Error> {
-        let len_bytes = self
-            .input
-            .get(start_position..end_position)
-            .ok_or(Error::NotComplete)?;
-        let options = ParseIntegerOptions::new();
-        let len = parse_with_options::<usize, &[u8], STANDARD>(len_bytes, &options)?;
-
-        let data = MiniVec::with_capacity(len);
-
-        let mut stack = Vec::new();
-        stack.push((data, len));
-
-        while let Some((mut current_vec, current_len)) = stack.pop() {
-            if current_len == 0 {
-                if stack.is_empty() {
-                    return Ok(Frame::Array {
-                        data: current_vec,
-                        attributes,
-                    });
-                } else if let Some((parent_vec, parent_len)) = stack.last_mut() {
-                    parent_vec.push(Frame::Array {
-                        data: current_vec,
-                        attributes: None,
-                    });
-                    *parent_len -= 1;
-                    continue;
-                }
-            }
-
-            match self.lexer.next() {
-                Some(Ok(tag)) => match tag.tag_type {
-                    TagType::Boolean => {
-                        let frame =
-                            self.parse_boolean(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::SimpleString => {
-                        let frame =
-                            self.parse_simple_string(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::SimpleError => {
-                        let frame =
-                            self.parse_simple_error(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Null => {
-                        let frame = Frame::Null { data: () };
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Integer => {
-                        let frame =
-                            self.parse_integer(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Double => {
-                        let frame =
-                            self.parse_double(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::BulkString => {
-                        let frame =
-                            self.parse_bulk_string(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::BulkError => {
-                        let frame =
-                            self.parse_bulk_error(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::VerbatimString => {
-                        let frame =
-                            self.parse_verbatim_string(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::BigNumber => {
-                        let frame =
-                            self.parse_big_number(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Array => {
-                        let new_len_bytes = self
-                            .input
-                            .get(tag.start_position..tag.end_position)
-                            .ok_or(Error::NotComplete)?;
-                        let new_len =
-                            parse_with_options::<usize, &[u8], STANDARD>(new_len_bytes, &options)?;
-
-                        let new_array = MiniVec::with_capacity(new_len);
-                        stack.push((current_vec, current_len));
-                        stack.push((new_array, new_len));
-                    }
-                    TagType::Map => {
-                        let frame = self.parse_map(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Set => {
-                        let frame = self.parse_set(tag.start_position, tag.end_position, None)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Push => {
-                        let frame = self.parse_push(tag.start_position, tag.end_position)?;
-                        current_vec.push(frame);
-                        stack.push((current_vec, current_len - 1));
-                    }
-                    TagType::Attribute => return Err(Error::InvalidBulkString),
-                    _ => return Err(Error::Unknown),
-                },
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            }
-        }
-
-        Err(Error::NotComplete)
-    }
-
-    fn parse_map(
-        &mut self,
-        start_position: usize,
-        end_position: usize,
-        attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
-    ) -> Result<Frame<'a>, Error> {
-        let len_bytes = self
-            .input
-            .get(start_position..end_position)
-            .ok_or(Error::NotComplete)?;
-        let options = ParseIntegerOptions::new();
-        let len = parse_with_options::<usize, &[u8], STANDARD>(len_bytes, &options)?;
-
-        let mut data = HashMap::with_capacity(len);
-
-        for _ in 0..len {
-            let key = match self.next_frame() {
-                Some(Ok(Frame::Map { data, attributes })) => return Err(Error::InvalidMap),
-                Some(Ok(Frame::Set { data, attributes })) => return Err(Error::InvalidMap),
-                Some(Ok(frame)) => frame,
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            };
-
-            let value = match self.next_frame() {
-                Some(Ok(frame)) => frame,
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            };
-
-            data.insert(key, value);
-        }
-
-        Ok(Frame::Map { data, attributes })
-    }
-
-    fn parse_set(
-        &mut self,
-        start_position: usize,
-        end_position: usize,
-        attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
-    ) -> Result<Frame<'a>, Error> {
-        let len_bytes = self
-            .input
-            .get(start_position..end_position)
-            .ok_or(Error::NotComplete)?;
-        let options = ParseIntegerOptions::new();
-        let len = parse_with_options::<usize, &[u8], STANDARD>(len_bytes, &options)?;
-
-        let mut data = HashSet::with_capacity(len);
-
-        for _ in 0..len {
-            let value = match self.next_frame() {
-                Some(Ok(Frame::Map { data, attributes })) => return Err(Error::InvalidSet),
-                Some(Ok(Frame::Set { data, attributes })) => return Err(Error::InvalidSet),
-                Some(Ok(frame)) => frame,
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            };
-
-            data.insert(value);
-        }
-
-        Ok(Frame::Set { data, attributes })
-    }
-
-    fn parse_push(
-        &mut self,
-        start_position: usize,
-        end_position: usize,
-    ) -> Result<Frame<'a>, Error> {
-        let len_bytes = self
-            .input
-            .get(start_position..end_position)
-            .ok_or(Error::NotComplete)?;
-        let options = ParseIntegerOptions::new();
-        let len = parse_with_options::<usize, &[u8], STANDARD>(len_bytes, &options)?;
-
-        let mut data = MiniVec::with_capacity(len);
-        for _ in 0..len {
-            match self.next_frame() {
-                Some(Ok(frame)) => {
-                    data.push(frame);
-                }
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            }
-        }
-
-        Ok(Frame::Push { data })
-    }
-
     #[inline(always)]
     fn parse_big_number(
         &self,
@@ -440,116 +520,13 @@ impl<'a> Ast<'a> {
         attributes: Option<HashMap<Frame<'a>, Frame<'a>>>,
     ) -> Result<Frame<'a>, Error> {
         match self.input.get(start_position..end_position) {
-            Some(data) => Ok(Frame::BigNumber { data, attributes }),
+            Some(data) => Ok(Frame::BigNumber {
+                data: check_big_number(data)?,
+                attributes,
+            }),
             None => Err(Error::NotComplete),
         }
     }
-
-    fn parse_attribute(
-        &mut self,
-        start_position: usize,
-        end_position: usize,
-    ) -> Result<Frame<'a>, Error> {
-        let len_bytes = self
-            .input
-            .get(start_position..end_position)
-            .ok_or(Error::NotComplete)?;
-        let options = ParseIntegerOptions::new();
-        let len = parse_with_options::<usize, &[u8], STANDARD>(len_bytes, &options)?;
-
-        let mut attributes = HashMap::with_capacity(len);
-        let mut func = || -> Option<Result<Frame<'a>, Error>> {
-            match self.lexer.next() {
-                Some(Ok(tag)) => match tag.tag_type {
-                    TagType::Boolean => {
-                        Some(self.parse_boolean(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::SimpleString => {
-                        Some(self.parse_simple_string(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::SimpleError => {
-                        Some(self.parse_simple_error(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::Null => Some(Ok(Frame::Null { data: () })),
-                    TagType::Integer => {
-                        Some(self.parse_integer(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::Double => {
-                        Some(self.parse_double(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::BulkString => {
-                        Some(self.parse_bulk_string(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::BulkError => {
-                        Some(self.parse_bulk_error(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::VerbatimString => {
-                        Some(self.parse_verbatim_string(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::BigNumber => {
-                        Some(self.parse_big_number(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::Array => {
-                        Some(self.parse_array(tag.start_position, tag.end_position, None))
-                    }
-                    TagType::Map => Some(Err(Error::InvalidMap)),
-                    TagType::Set => Some(Err(Error::InvalidSet)),
-                    _ => Some(Err(Error::Unknown)),
-                },
-                Some(Err(err)) => Some(Err(err)),
-                None => None,
-            }
-        };
-        for _ in 0..len {
-            let key = match func() {
-                Some(Ok(frame)) => frame,
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            };
-
-            let value = match func() {
-                Some(Ok(frame)) => frame,
-                Some(Err(err)) => return Err(err),
-                None => return Err(Error::NotComplete),
-            };
-            attributes.insert(key, value);
-        }
-        let attributes = Some(attributes);
-        match self.lexer.next() {
-            Some(Ok(tag)) => match tag.tag_type {
-                TagType::SimpleString => {
-                    self.parse_simple_string(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::SimpleError => {
-                    self.parse_simple_error(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::Integer => {
-                    self.parse_integer(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::BigNumber => {
-                    self.parse_big_number(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::Double => {
-                    self.parse_double(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::Boolean => {
-                    self.parse_boolean(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::BulkString => {
-                    self.parse_bulk_string(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::BulkError => {
-                    self.parse_bulk_error(tag.start_position, tag.end_position, attributes)
-                }
-                TagType::VerbatimString => {
-                    self.parse_verbatim_string(tag.start_position, tag.end_position, attributes)
-                }
-                _ => return Err(Error::Unknown),
-            },
-            Some(Err(e)) => return Err(e),
-            None => return Err(Error::NotComplete),
-        }
-    }
 }
 
 impl<'a> Iterator for Ast<'a> {
@@ -651,7 +628,8 @@ mod test {
                 data: mini_vec![Frame::BulkString {
                     data: b"bar",
                     attributes: None
-                }]
+                }],
+                attributes: None,
             })
         )
     }
@@ -0,0 +1,118 @@
+use lexical::to_string;
+
+use crate::Error;
+
+use super::Frame;
+
+/// Checks that `data` is a big number, `[+|-]<number>` as the RESP3 spec
+/// writes it: an optional sign followed by at least one digit.
+pub(crate) fn check_big_number(data: &[u8]) -> Result<&[u8], Error> {
+    let digits = match data.split_first() {
+        Some((b'+' | b'-', digits)) => digits,
+        _ => data,
+    };
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return Err(Error::InvalidBigNumber);
+    }
+    Ok(data)
+}
+
+/// The digits of a big number, owned so that a [`Frame::BigNumber`] can
+/// borrow them for encoding.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigNumberBuf {
+    digits: String,
+}
+
+impl BigNumberBuf {
+    pub fn as_bytes(&self) -> &[u8] {
+        self.digits.as_bytes()
+    }
+
+    pub fn as_frame(&self) -> Frame<'_> {
+        Frame::BigNumber {
+            data: self.as_bytes(),
+            attributes: None,
+        }
+    }
+}
+
+impl From<i128> for BigNumberBuf {
+    fn from(number: i128) -> Self {
+        Self {
+            digits: to_string(number),
+        }
+    }
+}
+
+impl From<u128> for BigNumberBuf {
+    fn from(number: u128) -> Self {
+        Self {
+            digits: to_string(number),
+        }
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<&num_bigint::BigInt> for BigNumberBuf {
+    fn from(number: &num_bigint::BigInt) -> Self {
+        Self {
+            digits: number.to_str_radix(10),
+        }
+    }
+}
+
+#[cfg(feature = "num-bigint")]
+impl From<num_bigint::BigInt> for BigNumberBuf {
+    fn from(number: num_bigint::BigInt) -> Self {
+        Self::from(&number)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_check_big_number() {
+        for valid in [
+            b"0".as_slice(),
+            b"-1",
+            b"+12",
+            b"3492890328409238509324850943850943825024385",
+        ] {
+            assert_eq!(check_big_number(valid), Ok(valid));
+        }
+        for invalid in [
+            b"".as_slice(),
+            b"-",
+            b"+",
+            b"+-1",
+            b"abc",
+            b"1.5",
+            b"1e5",
+            b"--1",
+            b" 1",
+        ] {
+            assert_eq!(check_big_number(invalid), Err(Error::InvalidBigNumber));
+        }
+
+        let frame = Frame::BigNumber {
+            data: b"+12",
+            attributes: None,
+        };
+        assert_eq!(frame.as_i128(), Some(12));
+        assert_eq!(frame.as_u128(), Some(12));
+    }
+
+    #[test]
+    fn test_big_number_buf() {
+        let buf = BigNumberBuf::from(i128::MIN);
+        assert_eq!(buf.as_bytes(), b"-170141183460469231731687303715884105728");
+        assert_eq!(buf.as_frame().as_i128(), Some(i128::MIN));
+
+        let buf = BigNumberBuf::from(u128::MAX);
+        assert_eq!(buf.as_frame().as_u128(), Some(u128::MAX));
+        assert_eq!(buf.as_frame().as_i128(), None);
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    common::{parse_decimal, parse_double, Error},
+    Remaining,
+};
+
+use super::{
+    big_number::check_big_number,
+    tag::{Tag, TagType},
+    Lexer,
+};
+
+/// One step of a pull parse. Every aggregate start, attributes included, is
+/// eventually matched by an `End`, even when empty.
+///
+/// An attribute's `End` is followed by the value it annotates.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Event<'a> {
+    SimpleString(&'a [u8]),
+    SimpleError(&'a [u8]),
+    Bulk(&'a [u8]),
+    BulkError(&'a [u8]),
+    VerbatimString { format: [u8; 3], data: &'a [u8] },
+    Null,
+    Integer(i64),
+    Double(f64),
+    Boolean(bool),
+    BigNumber(&'a [u8]),
+    ArrayStart(usize),
+    MapStart(usize),
+    SetStart(usize),
+    PushStart(usize),
+    AttributeStart(usize),
+    End,
+}
+
+#[derive(Debug)]
+struct Open {
+    // Elements still expected, two per entry for maps and attributes.
+    remaining: usize,
+    attribute: bool,
+}
+
+/// Pull parser yielding [`Event`]s straight from the lexer, so callers can
+/// build their own structures, or count and skip elements, without the
+/// allocations of a full [`Frame`](super::Frame) tree.
+#[derive(Debug)]
+pub struct EventParser<'a> {
+    input: &'a [u8],
+    lexer: Lexer<'a>,
+    stack: Vec<Open>,
+}
+
+impl<'a> EventParser<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            lexer: Lexer::new(input),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Number of aggregates currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Skips the next value, subtree and attributes included, without
+    /// reporting its events. At the end of an aggregate this consumes its
+    /// `End` instead.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        let depth = self.stack.len();
+        loop {
+            let attribute = self.stack.get(depth).is_some_and(|open| open.attribute);
+            match self.next() {
+                // The value an attribute annotates follows its `End`.
+                Some(Ok(Event::End)) if attribute && self.stack.len() == depth => {}
+                Some(Ok(_)) if self.stack.len() <= depth => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::NotComplete),
+            }
+        }
+    }
+
+    fn complete_value(&mut self) {
+        if let Some(open) = self.stack.last_mut() {
+            open.remaining -= 1;
+        }
+    }
+
+    fn open(&mut self, remaining: usize, attribute: bool) {
+        self.stack.push(Open {
+            remaining,
+            attribute,
+        });
+    }
+
+    fn slice(&self, tag: &Tag) -> Result<&'a [u8], Error> {
+        self.input
+            .get(tag.start_position..tag.end_position)
+            .ok_or(Error::NotComplete)
+    }
+
+    fn len(&self, tag: &Tag) -> Result<usize, Error> {
+        parse_decimal::<usize>(self.slice(tag)?)
+    }
+
+    fn event(&mut self, tag: Tag) -> Result<Event<'a>, Error> {
+        let event = match tag.tag_type {
+            TagType::SimpleString => Event::SimpleString(self.slice(&tag)?),
+            TagType::SimpleError => Event::SimpleError(self.slice(&tag)?),
+            TagType::BulkString => Event::Bulk(self.slice(&tag)?),
+            TagType::BulkError => Event::BulkError(self.slice(&tag)?),
+            TagType::BigNumber => Event::BigNumber(check_big_number(self.slice(&tag)?)?),
+            TagType::Null => Event::Null,
+            TagType::Integer => Event::Integer(parse_decimal::<i64>(self.slice(&tag)?)?),
+            TagType::Double => Event::Double(parse_double(self.slice(&tag)?)?),
+            TagType::Boolean => match self.slice(&tag)? {
+                b"t" => Event::Boolean(true),
+                b"f" => Event::Boolean(false),
+                _ => return Err(Error::InvalidBoolean),
+            },
+            TagType::VerbatimString => match self.slice(&tag)? {
+                [a, b, c, b':', data @ ..] => Event::VerbatimString {
+                    format: [*a, *b, *c],
+                    data,
+                },
+                _ => return Err(Error::InvalidVerbatimString),
+            },
+            TagType::Array => {
+                let len = self.len(&tag)?;
+                self.open(len, false);
+                return Ok(Event::ArrayStart(len));
+            }
+            TagType::Set => {
+                let len = self.len(&tag)?;
+                self.open(len, false);
+                return Ok(Event::SetStart(len));
+            }
+            TagType::Push => {
+                let len = self.len(&tag)?;
+                self.open(len, false);
+                return Ok(Event::PushStart(len));
+            }
+            TagType::Map => {
+                let len = self.len(&tag)?;
+                self.open(len.checked_mul(2).ok_or(Error::InvalidMap)?, false);
+                return Ok(Event::MapStart(len));
+            }
+            TagType::Attribute => {
+                let len = self.len(&tag)?;
+                self.open(len.checked_mul(2).ok_or(Error::InvalidMap)?, true);
+                return Ok(Event::AttributeStart(len));
+            }
+        };
+        self.complete_value();
+        Ok(event)
+    }
+}
+
+impl<'a> Iterator for EventParser<'a> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stack.last().is_some_and(|open| open.remaining == 0) {
+            let closed = self.stack.pop()?;
+            // An attribute is not an element of its parent; the value it
+            // annotates is.
+            if !closed.attribute {
+                self.complete_value();
+            }
+            return Some(Ok(Event::End));
+        }
+
+        match self.lexer.next() {
+            Some(Ok(tag)) => Some(self.event(tag)),
+            Some(Err(e)) => Some(Err(e)),
+            None if self.stack.is_empty() => None,
+            None => Some(Err(Error::NotComplete)),
+        }
+    }
+}
+
+impl<'a> Remaining for EventParser<'a> {
+    fn remaining(&self) -> usize {
+        self.lexer.remaining()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let input = b"%2\r\n+a\r\n*0\r\n$1\r\nb\r\n~2\r\n#t\r\n,1.5\r\n|1\r\n+ttl\r\n:-3\r\n=7\r\ntxt:abc\r\n>1\r\n_\r\n";
+        let events = EventParser::new(input)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![
+                Event::MapStart(2),
+                Event::SimpleString(b"a"),
+                Event::ArrayStart(0),
+                Event::End,
+                Event::Bulk(b"b"),
+                Event::SetStart(2),
+                Event::Boolean(true),
+                Event::Double(1.5),
+                Event::End,
+                Event::End,
+                Event::AttributeStart(1),
+                Event::SimpleString(b"ttl"),
+                Event::Integer(-3),
+                Event::End,
+                Event::VerbatimString {
+                    format: *b"txt",
+                    data: b"abc",
+                },
+                Event::PushStart(1),
+                Event::Null,
+                Event::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let input = b"*3\r\n%1\r\n+k\r\n*1\r\n:1\r\n|1\r\n+a\r\n+b\r\n:2\r\n:3\r\n";
+        let mut parser = EventParser::new(input);
+        assert_eq!(parser.next(), Some(Ok(Event::ArrayStart(3))));
+        parser.skip_value().unwrap();
+        parser.skip_value().unwrap();
+        assert_eq!(parser.depth(), 1);
+        assert_eq!(parser.next(), Some(Ok(Event::Integer(3))));
+        assert_eq!(parser.next(), Some(Ok(Event::End)));
+        assert_eq!(parser.next(), None);
+
+        let mut parser = EventParser::new(b"%1\r\n+k\r\n");
+        assert_eq!(parser.skip_value(), Err(Error::NotComplete));
+    }
+}
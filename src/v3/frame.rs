@@ -7,13 +7,18 @@ use std::{
     io::{Result as IoResult, Write},
 };
 
-use lexical::to_string;
+use bytes::BufMut;
+use lexical::{to_string, BUFFER_SIZE};
 
-use crate::{EncodeLen, Error};
+use super::{big_number::check_big_number, VerbatimFormat};
+use crate::{
+    common::{double_len, format_double, int_len, put_number, uint_len, INVALIDATE},
+    EncodeLen, Error, Invalidation, RedisError, VectoredEncoder,
+};
 
 type Attributes<'a> = HashMap<Frame<'a>, Frame<'a>>;
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub enum Frame<'a> {
     SimpleString {
         data: &'a [u8],
@@ -29,6 +34,7 @@ pub enum Frame<'a> {
     },
     Null {
         data: (),
+        attributes: Option<Attributes<'a>>,
     },
     Integer {
         data: isize,
@@ -36,6 +42,10 @@ pub enum Frame<'a> {
     },
     Double {
         data: f64,
+        /// The text the double was decoded from, written back as is when
+        /// encoding. Only kept when asked for with
+        /// [`DecodeIter::keep_double_text`](super::DecodeIter::keep_double_text).
+        raw: Option<&'a [u8]>,
         attributes: Option<Attributes<'a>>,
     },
     BulkString {
@@ -64,6 +74,7 @@ pub enum Frame<'a> {
     },
     Push {
         data: MiniVec<Frame<'a>>,
+        attributes: Option<Attributes<'a>>,
     },
     BigNumber {
         data: &'a [u8],
@@ -77,9 +88,11 @@ impl<'a> Hash for Frame<'a> {
             Self::SimpleString { data, attributes } => data.hash(state),
             Self::SimpleError { data, attributes } => data.hash(state),
             Self::Boolean { data, attributes } => data.hash(state),
-            Self::Null { data } => data.hash(state),
+            Self::Null { data, .. } => data.hash(state),
             Self::Integer { data, attributes } => data.hash(state),
-            Self::Double { data, attributes } => data.to_be_bytes().hash(state),
+            Self::Double {
+                data, attributes, ..
+            } => data.to_be_bytes().hash(state),
             Self::BulkString { data, attributes } => data.hash(state),
             Self::BulkError { data, attributes } => data.hash(state),
             Self::VerbatimString { data, attributes } => data.hash(state),
@@ -90,16 +103,127 @@ impl<'a> Hash for Frame<'a> {
     }
 }
 
+impl<'a> PartialEq for Frame<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::SimpleString { data, attributes },
+                Self::SimpleString {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            )
+            | (
+                Self::SimpleError { data, attributes },
+                Self::SimpleError {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            )
+            | (
+                Self::BulkString { data, attributes },
+                Self::BulkString {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            )
+            | (
+                Self::BulkError { data, attributes },
+                Self::BulkError {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            )
+            | (
+                Self::BigNumber { data, attributes },
+                Self::BigNumber {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            (
+                Self::Boolean { data, attributes },
+                Self::Boolean {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            (
+                Self::Null { attributes, .. },
+                Self::Null {
+                    attributes: other_attributes,
+                    ..
+                },
+            ) => attributes == other_attributes,
+            (
+                Self::Integer { data, attributes },
+                Self::Integer {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            // Doubles compare by their bits, as `Hash` sees them, so that a
+            // `nan` equals itself and `0.0` and `-0.0` stay apart as keys.
+            // `raw` only records how the double was spelled on the wire, and
+            // takes no part in either.
+            (
+                Self::Double {
+                    data, attributes, ..
+                },
+                Self::Double {
+                    data: other_data,
+                    attributes: other_attributes,
+                    ..
+                },
+            ) => data.to_bits() == other_data.to_bits() && attributes == other_attributes,
+            (
+                Self::VerbatimString { data, attributes },
+                Self::VerbatimString {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            (
+                Self::Array { data, attributes },
+                Self::Array {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            )
+            | (
+                Self::Push { data, attributes },
+                Self::Push {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            (
+                Self::Map { data, attributes },
+                Self::Map {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            (
+                Self::Set { data, attributes },
+                Self::Set {
+                    data: other_data,
+                    attributes: other_attributes,
+                },
+            ) => data == other_data && attributes == other_attributes,
+            _ => false,
+        }
+    }
+}
+
 impl<'a> Eq for Frame<'a> {}
 
 impl<'a> Frame<'a> {
     fn attributes_len(attributes: &Option<Attributes<'a>>) -> usize {
         if let Some(attributes) = attributes {
-            let attributes_len = attributes.len();
-            let attributes_len_text = to_string(attributes_len);
             attributes
                 .iter()
-                .fold(3 + attributes_len_text.len(), |prev_len, (key, value)| {
+                .fold(3 + uint_len(attributes.len()), |prev_len, (key, value)| {
                     prev_len + key.encode_len() + value.encode_len()
                 })
         } else {
@@ -114,9 +238,9 @@ impl<'a> Frame<'a> {
         if let Some(attributes) = attributes {
             let attributes_len = attributes.len();
             let attributes_len_text = to_string(attributes_len);
-            writer.write(b"|")?;
-            writer.write(attributes_len_text.as_bytes())?;
-            writer.write(b"\r\n")?;
+            writer.write_all(b"|")?;
+            writer.write_all(attributes_len_text.as_bytes())?;
+            writer.write_all(b"\r\n")?;
             attributes.iter().try_for_each(|(key, value)| {
                 key.encode_with_writer(writer)?;
                 value.encode_with_writer(writer)?;
@@ -126,15 +250,267 @@ impl<'a> Frame<'a> {
             Ok(())
         }
     }
+
+    fn attributes_encode_to_buf<B>(attributes: &Option<Attributes<'a>>, buf: &mut B)
+    where
+        B: BufMut,
+    {
+        if let Some(attributes) = attributes {
+            buf.put_u8(b'|');
+            put_number(buf, attributes.len());
+            buf.put_slice(b"\r\n");
+            for (key, value) in attributes {
+                key.encode_to_buf(buf);
+                value.encode_to_buf(buf);
+            }
+        }
+    }
+
+    fn attributes_encode_vectored(
+        attributes: &Option<Attributes<'a>>,
+        encoder: &mut VectoredEncoder<'a>,
+    ) {
+        if let Some(attributes) = attributes {
+            let attributes_len_text = to_string(attributes.len());
+            encoder.extend(b"|");
+            encoder.extend(attributes_len_text.as_bytes());
+            encoder.extend(b"\r\n");
+            for (key, value) in attributes {
+                key.encode_vectored(encoder);
+                value.encode_vectored(encoder);
+            }
+        }
+    }
+}
+
+impl<'a> Frame<'a> {
+    /// Returns the payload of a simple or bulk string frame.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            Self::SimpleString { data, .. } | Self::BulkString { data, .. } => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Returns the attributes sent ahead of the frame, whatever its type.
+    pub fn attributes(&self) -> Option<&Attributes<'a>> {
+        match self {
+            Self::SimpleString { attributes, .. }
+            | Self::SimpleError { attributes, .. }
+            | Self::Boolean { attributes, .. }
+            | Self::Null { attributes, .. }
+            | Self::Integer { attributes, .. }
+            | Self::Double { attributes, .. }
+            | Self::BulkString { attributes, .. }
+            | Self::BulkError { attributes, .. }
+            | Self::VerbatimString { attributes, .. }
+            | Self::Array { attributes, .. }
+            | Self::Map { attributes, .. }
+            | Self::Set { attributes, .. }
+            | Self::Push { attributes, .. }
+            | Self::BigNumber { attributes, .. } => attributes.as_ref(),
+        }
+    }
+
+    /// Converts a big number to an `i128`, or `None` if it does not fit.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            Self::BigNumber { data, .. } => lexical::parse(check_big_number(data).ok()?).ok(),
+            _ => None,
+        }
+    }
+
+    /// Converts a big number to a `u128`, or `None` if it is negative or
+    /// does not fit.
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            Self::BigNumber { data, .. } => lexical::parse(check_big_number(data).ok()?).ok(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "num-bigint")]
+    pub fn as_big_int(&self) -> Option<num_bigint::BigInt> {
+        match self {
+            Self::BigNumber { data, .. } => {
+                num_bigint::BigInt::parse_bytes(check_big_number(data).ok()?, 10)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn verbatim_format(&self) -> Option<VerbatimFormat> {
+        match self {
+            Self::VerbatimString { data, .. } => Some(VerbatimFormat::from(data.0)),
+            _ => None,
+        }
+    }
+
+    /// Returns the payload of a verbatim string, if it is valid UTF-8.
+    pub fn as_verbatim_str(&self) -> Option<&'a str> {
+        match self {
+            Self::VerbatimString { data, .. } => std::str::from_utf8(data.1).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn is_push(&self) -> bool {
+        matches!(self, Self::Push { .. })
+    }
+
+    pub fn as_error(&self) -> Option<RedisError<'a>> {
+        match self {
+            Self::SimpleError { data, .. } | Self::BulkError { data, .. } => {
+                Some(RedisError::parse(data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Decodes a `>2 invalidate [keys]` push, where a null key list means the
+    /// whole keyspace was flushed.
+    pub fn as_invalidation(&self) -> Option<Invalidation<'a>> {
+        let Self::Push { data, .. } = self else {
+            return None;
+        };
+        match &data[..] {
+            [kind, keys] if kind.as_bytes() == Some(INVALIDATE) => match keys {
+                Self::Null { .. } => Some(Invalidation::Flush),
+                Self::Array { data, .. } => data
+                    .iter()
+                    .map(|key| key.as_bytes())
+                    .collect::<Option<Vec<_>>>()
+                    .map(Invalidation::Keys),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
 impl<'a> Frame<'a> {
     pub fn encode(&self) -> Vec<u8> {
         let mut buffer = Vec::with_capacity(self.encode_len());
-        self.encode_with_writer(&mut buffer);
+        self.encode_to_buf(&mut buffer);
         buffer
     }
 
+    /// Encodes into `buf` without allocating; reserve `encode_len()` bytes
+    /// beforehand to avoid growing it more than once.
+    pub fn encode_to_buf<B>(&self, buf: &mut B)
+    where
+        B: BufMut,
+    {
+        match self {
+            Self::SimpleString { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'+');
+                buf.put_slice(data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::SimpleError { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'-');
+                buf.put_slice(data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::Boolean { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_slice(if *data { b"#t\r\n" } else { b"#f\r\n" });
+            }
+            Self::Null { attributes, .. } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_slice(b"_\r\n");
+            }
+            Self::Integer { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b':');
+                put_number(buf, *data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::Double {
+                data,
+                raw,
+                attributes,
+            } => {
+                let mut bytes = [0u8; BUFFER_SIZE];
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b',');
+                buf.put_slice(raw.unwrap_or_else(|| format_double(*data, &mut bytes)));
+                buf.put_slice(b"\r\n");
+            }
+            Self::BulkString { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'$');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                buf.put_slice(data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::BulkError { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'!');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                buf.put_slice(data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::BigNumber { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'(');
+                buf.put_slice(data);
+                buf.put_slice(b"\r\n");
+            }
+            Self::VerbatimString { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'=');
+                put_number(buf, data.1.len() + 4);
+                buf.put_slice(b"\r\n");
+                buf.put_slice(data.0.as_slice());
+                buf.put_u8(b':');
+                buf.put_slice(data.1);
+                buf.put_slice(b"\r\n");
+            }
+            Self::Array { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'*');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                for frame in data {
+                    frame.encode_to_buf(buf);
+                }
+            }
+            Self::Map { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'%');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                for (key, value) in data {
+                    key.encode_to_buf(buf);
+                    value.encode_to_buf(buf);
+                }
+            }
+            Self::Set { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'~');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                for frame in data {
+                    frame.encode_to_buf(buf);
+                }
+            }
+            Self::Push { data, attributes } => {
+                Self::attributes_encode_to_buf(attributes, buf);
+                buf.put_u8(b'>');
+                put_number(buf, data.len());
+                buf.put_slice(b"\r\n");
+                for frame in data {
+                    frame.encode_to_buf(buf);
+                }
+            }
+        }
+    }
+
     pub fn encode_with_writer<W>(&self, writer: &mut W) -> IoResult<()>
     where
         W: Write,
@@ -142,84 +518,90 @@ impl<'a> Frame<'a> {
         match self {
             Self::SimpleString { data, attributes } => {
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"+")?;
-                writer.write(&data)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"+")?;
+                writer.write_all(data)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::SimpleError { data, attributes } => {
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"-")?;
-                writer.write(&data)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"-")?;
+                writer.write_all(data)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::Boolean { data, attributes } => {
                 let bool_text = if *data { b"t" } else { b"f" };
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"#")?;
-                writer.write(bool_text)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"#")?;
+                writer.write_all(bool_text)?;
+                writer.write_all(b"\r\n")?;
             }
-            Self::Null { data: _ } => {
-                writer.write(b"_\r\n")?;
+            Self::Null { attributes, .. } => {
+                Self::attibutes_encode(attributes, writer)?;
+                writer.write_all(b"_\r\n")?;
             }
             Self::Integer { data, attributes } => {
                 let text = to_string(*data);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b":")?;
-                writer.write(text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b":")?;
+                writer.write_all(text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
             }
-            Self::Double { data, attributes } => {
-                let text = to_string(*data);
+            Self::Double {
+                data,
+                raw,
+                attributes,
+            } => {
+                let mut bytes = [0u8; BUFFER_SIZE];
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b",")?;
-                writer.write(text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b",")?;
+                writer.write_all(raw.unwrap_or_else(|| format_double(*data, &mut bytes)))?;
+                writer.write_all(b"\r\n")?;
             }
             Self::BulkString { data, attributes } => {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"$")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
-                writer.write(data)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"$")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
+                writer.write_all(data)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::BulkError { data, attributes } => {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"!")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
-                writer.write(data)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"!")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
+                writer.write_all(data)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::BigNumber { data, attributes } => {
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"(")?;
-                writer.write(data)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"(")?;
+                writer.write_all(data)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::VerbatimString { data, attributes } => {
                 let data_len = data.1.len();
                 let data_len_text = to_string(data_len + 4);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"=")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(data.0.as_slice())?;
-                writer.write(b":")?;
-                writer.write(data.1)?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"=")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
+                writer.write_all(data.0.as_slice())?;
+                writer.write_all(b":")?;
+                writer.write_all(data.1)?;
+                writer.write_all(b"\r\n")?;
             }
             Self::Array { data, attributes } => {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"*")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"*")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
                 for frame in data {
                     frame.encode_with_writer(writer)?;
                 }
@@ -228,9 +610,9 @@ impl<'a> Frame<'a> {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"%")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"%")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
                 for (key, value) in data {
                     key.encode_with_writer(writer)?;
                     value.encode_with_writer(writer)?;
@@ -240,19 +622,20 @@ impl<'a> Frame<'a> {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
                 Self::attibutes_encode(attributes, writer)?;
-                writer.write(b"~")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                writer.write_all(b"~")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
                 for frame in data {
                     frame.encode_with_writer(writer)?;
                 }
             }
-            Self::Push { data } => {
+            Self::Push { data, attributes } => {
                 let data_len = data.len();
                 let data_len_text = to_string(data_len);
-                writer.write(b">")?;
-                writer.write(data_len_text.as_bytes())?;
-                writer.write(b"\r\n")?;
+                Self::attibutes_encode(attributes, writer)?;
+                writer.write_all(b">")?;
+                writer.write_all(data_len_text.as_bytes())?;
+                writer.write_all(b"\r\n")?;
                 for frame in data {
                     frame.encode_with_writer(writer)?;
                 }
@@ -261,81 +644,184 @@ impl<'a> Frame<'a> {
 
         Ok(())
     }
-}
 
-impl<'a> EncodeLen for Frame<'a> {
-    fn encode_len(&self) -> usize {
+    /// Encodes into `encoder`, referencing string payloads instead of copying
+    /// them.
+    pub fn encode_vectored(&self, encoder: &mut VectoredEncoder<'a>) {
         match self {
             Self::SimpleString { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                data.len() + 3 + attributes_len
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"+");
+                encoder.extend(data);
+                encoder.extend(b"\r\n");
             }
             Self::SimpleError { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                data.len() + 3 + attributes_len
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"-");
+                encoder.extend(data);
+                encoder.extend(b"\r\n");
             }
             Self::Boolean { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                4 + attributes_len
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(if *data { b"#t\r\n" } else { b"#f\r\n" });
+            }
+            Self::Null { attributes, .. } => {
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"_\r\n");
             }
-            Self::Null { data } => 3,
             Self::Integer { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
                 let text = to_string(*data);
-                text.len() + 3 + attributes_len
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b":");
+                encoder.extend(text.as_bytes());
+                encoder.extend(b"\r\n");
             }
-            Self::Double { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(*data);
-                text.len() + 3 + attributes_len
+            Self::Double {
+                data,
+                raw,
+                attributes,
+            } => {
+                let mut bytes = [0u8; BUFFER_SIZE];
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b",");
+                encoder.extend(raw.unwrap_or_else(|| format_double(*data, &mut bytes)));
+                encoder.extend(b"\r\n");
             }
             Self::BulkString { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.len());
-                text.len() + data.len() + 5 + attributes_len
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"$");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                encoder.payload(data);
+                encoder.extend(b"\r\n");
             }
             Self::BulkError { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.len());
-                text.len() + data.len() + 5 + attributes_len
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"!");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                encoder.payload(data);
+                encoder.extend(b"\r\n");
+            }
+            Self::BigNumber { data, attributes } => {
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"(");
+                encoder.extend(data);
+                encoder.extend(b"\r\n");
             }
             Self::VerbatimString { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.1.len());
-                text.len() + data.1.len() + 8 + attributes_len
+                let data_len_text = to_string(data.1.len() + 4);
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"=");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                encoder.extend(data.0.as_slice());
+                encoder.extend(b":");
+                encoder.payload(data.1);
+                encoder.extend(b"\r\n");
             }
             Self::Array { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.len());
-                text.len()
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"*");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                for frame in data {
+                    frame.encode_vectored(encoder);
+                }
+            }
+            Self::Map { data, attributes } => {
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"%");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                for (key, value) in data {
+                    key.encode_vectored(encoder);
+                    value.encode_vectored(encoder);
+                }
+            }
+            Self::Set { data, attributes } => {
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b"~");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                for frame in data {
+                    frame.encode_vectored(encoder);
+                }
+            }
+            Self::Push { data, attributes } => {
+                let data_len_text = to_string(data.len());
+                Self::attributes_encode_vectored(attributes, encoder);
+                encoder.extend(b">");
+                encoder.extend(data_len_text.as_bytes());
+                encoder.extend(b"\r\n");
+                for frame in data {
+                    frame.encode_vectored(encoder);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> EncodeLen for Frame<'a> {
+    fn encode_len(&self) -> usize {
+        match self {
+            Self::SimpleString { data, attributes }
+            | Self::SimpleError { data, attributes }
+            | Self::BigNumber { data, attributes } => {
+                Self::attributes_len(attributes) + 3 + data.len()
+            }
+            Self::Boolean { attributes, .. } => Self::attributes_len(attributes) + 4,
+            Self::Null { attributes, .. } => Self::attributes_len(attributes) + 3,
+            Self::Integer { data, attributes } => {
+                Self::attributes_len(attributes) + 3 + int_len(*data as i64)
+            }
+            Self::Double {
+                data,
+                raw,
+                attributes,
+            } => {
+                let text_len = raw.map_or_else(|| double_len(*data), <[u8]>::len);
+                Self::attributes_len(attributes) + 3 + text_len
+            }
+            Self::BulkString { data, attributes } | Self::BulkError { data, attributes } => {
+                Self::attributes_len(attributes) + 5 + uint_len(data.len()) + data.len()
+            }
+            Self::VerbatimString { data, attributes } => {
+                let data_len = data.1.len();
+                Self::attributes_len(attributes) + 9 + uint_len(data_len + 4) + data_len
+            }
+            Self::Array { data, attributes } => {
+                Self::attributes_len(attributes)
+                    + 3
+                    + uint_len(data.len())
                     + data.iter().map(|frame| frame.encode_len()).sum::<usize>()
-                    + 5
-                    + attributes_len
             }
             Self::Map { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.len());
-                text.len()
+                Self::attributes_len(attributes)
+                    + 3
+                    + uint_len(data.len())
                     + data
                         .iter()
                         .map(|(key, value)| key.encode_len() + value.encode_len())
                         .sum::<usize>()
-                    + 5
-                    + attributes_len
             }
             Self::Set { data, attributes } => {
-                let attributes_len = Self::attributes_len(attributes);
-                let text = to_string(data.len());
-                text.len()
+                Self::attributes_len(attributes)
+                    + 3
+                    + uint_len(data.len())
                     + data.iter().map(|frame| frame.encode_len()).sum::<usize>()
-                    + 5
-                    + attributes_len
             }
-            Self::Push { data } => {
-                let text = to_string(data.len());
-                text.len() + data.iter().map(|frame| frame.encode_len()).sum::<usize>() + 5
+            Self::Push { data, attributes } => {
+                Self::attributes_len(attributes)
+                    + 3
+                    + uint_len(data.len())
+                    + data.iter().map(|frame| frame.encode_len()).sum::<usize>()
             }
-            _ => 0,
         }
     }
 }
@@ -378,7 +864,10 @@ impl<'a> TryFrom<V2Frame<'a>> for Frame<'a> {
                             stack.push((current_vec, queue));
                         }
                         Some(V2Frame::Null) => {
-                            let frame = Self::Null { data: () };
+                            let frame = Self::Null {
+                                data: (),
+                                attributes: None,
+                            };
                             current_vec.push(frame);
                             stack.push((current_vec, queue));
                         }
@@ -429,7 +918,10 @@ impl<'a> TryFrom<V2Frame<'a>> for Frame<'a> {
                 data: data as isize,
                 attributes: None,
             }),
-            V2Frame::Null => Ok(Self::Null { data: () }),
+            V2Frame::Null => Ok(Self::Null {
+                data: (),
+                attributes: None,
+            }),
             V2Frame::SimpleString(data) => Ok(Self::SimpleString {
                 data,
                 attributes: None,
@@ -467,7 +959,10 @@ mod test {
                             data: 45,
                             attributes: None,
                         },
-                        Frame::Null { data: () },
+                        Frame::Null {
+                            data: (),
+                            attributes: None,
+                        },
                         Frame::BulkString {
                             data: b"str",
                             attributes: None,
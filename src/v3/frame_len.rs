@@ -0,0 +1,232 @@
+use super::{ast::Slot, big_number::check_big_number, tag::TagType};
+use crate::common::{line, parse_decimal, parse_double, skip_payload, Error, FrameScan, MAX_DEPTH};
+
+/// Walks the frame header by header, so nothing is allocated. Each open
+/// aggregate keeps only the slot its elements fill and how many values it
+/// still owes, on a stack no deeper than [`MAX_DEPTH`], which is enough to
+/// hold the frame to the rules decoding applies.
+///
+/// Maps and attributes owe two values per entry and read a key whenever they
+/// owe an even number. Attributes precede the value they annotate, so closing
+/// one settles nothing in the aggregate around it.
+pub(crate) fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+    resume(&mut FrameScan::new(), input)
+}
+
+/// The slot the elements of an aggregate with type byte `first` fill.
+fn slot(first: u8) -> Slot {
+    match first {
+        b'%' => Slot::MapKey,
+        b'~' => Slot::SetMember,
+        b'|' => Slot::Attribute,
+        _ => Slot::Value,
+    }
+}
+
+/// Carries on measuring from `scan`, which only moves past a line once the
+/// line and its payload have fully arrived.
+pub(crate) fn resume(scan: &mut FrameScan, input: &[u8]) -> Result<Option<usize>, Error> {
+    loop {
+        let (first, follow, next) = match line(input, scan.position) {
+            Some(line) => line?,
+            None => return Ok(None),
+        };
+
+        let payload = |len: usize, error: Error| skip_payload(input, next, len, error);
+        let (skipped, aggregate) = match first {
+            b'+' | b'-' => (None, None),
+            b'_' if follow.is_empty() => (None, None),
+            b'_' => return Err(Error::InvalidNull),
+            b',' => {
+                parse_double(follow)?;
+                (None, None)
+            }
+            b'(' => {
+                check_big_number(follow)?;
+                (None, None)
+            }
+            b':' => {
+                parse_decimal::<i64>(follow)?;
+                (None, None)
+            }
+            b'#' => match follow {
+                b"t" | b"f" => (None, None),
+                _ => return Err(Error::InvalidBoolean),
+            },
+            b'$' => match parse_decimal::<isize>(follow)? {
+                -1 => (None, None),
+                len if len < 0 => return Err(Error::InvalidBulkString),
+                len => (Some(payload(len as usize, Error::InvalidBulkString)?), None),
+            },
+            b'!' => (
+                Some(payload(
+                    parse_decimal::<usize>(follow)?,
+                    Error::InvalidError,
+                )?),
+                None,
+            ),
+            b'=' => match parse_decimal::<usize>(follow)? {
+                len if len < 4 => return Err(Error::InvalidVerbatimString),
+                len => match payload(len, Error::InvalidVerbatimString)? {
+                    Some(_) if input[next + 3] != b':' => return Err(Error::InvalidVerbatimString),
+                    skipped => (Some(skipped), None),
+                },
+            },
+            b'*' => match parse_decimal::<isize>(follow)? {
+                -1 => (None, None),
+                len if len < 0 => return Err(Error::InvalidArray),
+                _ => (None, Some(TagType::Array)),
+            },
+            b'>' => {
+                parse_decimal::<usize>(follow)?;
+                (None, Some(TagType::Push))
+            }
+            b'~' => (None, Some(TagType::Set)),
+            b'%' => (None, Some(TagType::Map)),
+            b'|' => (None, Some(TagType::Attribute)),
+            _ => return Err(Error::Unknown),
+        };
+
+        let position = match skipped {
+            Some(Some(end)) => end,
+            Some(None) => return Ok(None),
+            None => next,
+        };
+
+        if let Some(tag_type) = aggregate {
+            let (outer, owed) = scan.levels[scan.depth];
+            let parent = match slot(outer) {
+                Slot::MapKey if owed % 2 == 1 => Slot::Value,
+                parent => parent,
+            };
+            parent.check(&tag_type)?;
+            if scan.depth >= MAX_DEPTH {
+                return Err(Error::TooDeep);
+            }
+            let len = parse_decimal::<usize>(follow)?;
+            let owed = match tag_type {
+                TagType::Map => len.checked_mul(2).ok_or(Error::InvalidMap)?,
+                TagType::Attribute if scan.annotated => return Err(Error::Unknown),
+                TagType::Attribute => len.checked_mul(2).ok_or(Error::InvalidMap)?,
+                _ => len,
+            };
+            scan.position = position;
+            scan.annotated = false;
+            if owed > 0 {
+                scan.depth += 1;
+                scan.levels[scan.depth] = (first, owed);
+                continue;
+            }
+            if let TagType::Attribute = tag_type {
+                scan.annotated = true;
+                continue;
+            }
+        }
+        scan.position = position;
+        scan.annotated = false;
+
+        // Settle the value just read, closing every aggregate it completes.
+        loop {
+            let owed = &mut scan.levels[scan.depth].1;
+            *owed -= 1;
+            if *owed > 0 {
+                break;
+            }
+            if scan.depth == 0 {
+                return Ok(Some(scan.position));
+            }
+            scan.depth -= 1;
+            if let Slot::Attribute = slot(scan.levels[scan.depth + 1].0) {
+                scan.annotated = true;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_frame_len() {
+        assert_eq!(frame_len(b"_\r\n#t\r\n"), Ok(Some(3)));
+        assert_eq!(frame_len(b"$12\r\nhello\r\nworld\r\n"), Ok(Some(19)));
+        assert_eq!(frame_len(b"=8\r\ntxt:a\r\nb\r\n"), Ok(Some(14)));
+        assert_eq!(
+            frame_len(b"%2\r\n+a\r\n:1\r\n+b\r\n~1\r\n,1.5\r\n"),
+            Ok(Some(26))
+        );
+        assert_eq!(
+            frame_len(b"|1\r\n+ttl\r\n:3\r\n#t\r\n+next\r\n"),
+            Ok(Some(18))
+        );
+        assert_eq!(frame_len(b">1\r\n|1\r\n+a\r\n:1\r\n(12\r\n"), Ok(Some(21)));
+        assert_eq!(frame_len(b"*0\r\n"), Ok(Some(4)));
+
+        assert_eq!(frame_len(b"|1\r\n+ttl\r\n:3\r\n"), Ok(None));
+        assert_eq!(frame_len(b"%1\r\n+a\r\n"), Ok(None));
+        assert_eq!(frame_len(b"!3\r\nER"), Ok(None));
+
+        assert_eq!(frame_len(b"#x\r\n"), Err(Error::InvalidBoolean));
+        assert_eq!(frame_len(b"!3\r\nERRx\r\n"), Err(Error::InvalidError));
+        assert_eq!(frame_len(b"*-3\r\n"), Err(Error::InvalidArray));
+        assert_eq!(frame_len(b"?\r\n"), Err(Error::Unknown));
+        assert!(frame_len(b"~x\r\n").is_err());
+        assert_eq!(frame_len(b",abc\r\n"), Err(Error::InvalidDouble));
+        assert_eq!(frame_len(b"(1.5\r\n"), Err(Error::InvalidBigNumber));
+        assert_eq!(frame_len(b"_x\r\n"), Err(Error::InvalidNull));
+
+        // Aggregates may only go where decoding takes them.
+        assert_eq!(frame_len(b"~1\r\n%0\r\n"), Err(Error::InvalidSet));
+        assert_eq!(frame_len(b"%1\r\n%0\r\n:1\r\n"), Err(Error::InvalidMap));
+        assert_eq!(frame_len(b"%1\r\n+a\r\n%0\r\n"), Ok(Some(12)));
+        assert_eq!(
+            frame_len(b"|1\r\n+a\r\n%1\r\n+b\r\n+c\r\n:1\r\n"),
+            Err(Error::InvalidMap)
+        );
+        assert_eq!(
+            frame_len(b"|1\r\n+a\r\n:1\r\n|1\r\n+b\r\n:2\r\n:3\r\n"),
+            Err(Error::Unknown)
+        );
+        assert_eq!(frame_len(b"|0\r\n|0\r\n:3\r\n"), Err(Error::Unknown));
+
+        let mut input = b"*1\r\n".repeat(MAX_DEPTH);
+        input.extend_from_slice(b"%0\r\n");
+        assert_eq!(frame_len(&input), Err(Error::TooDeep));
+        assert_eq!(frame_len(&input[4..]), Ok(Some(MAX_DEPTH * 4)));
+
+        // Lengths no input could hold fail instead of overflowing the count.
+        assert_eq!(
+            frame_len(b"~18446744073709551615\r\n~5\r\n"),
+            Err(Error::InvalidSet)
+        );
+        assert_eq!(
+            frame_len(b"%9223372036854775807\r\n%9223372036854775807\r\n"),
+            Err(Error::InvalidMap)
+        );
+        assert_eq!(
+            frame_len(b"|9223372036854775808\r\n"),
+            Err(Error::InvalidMap)
+        );
+    }
+
+    #[test]
+    fn test_resume() {
+        let input = b"|1\r\n+ttl\r\n:3\r\n%2\r\n+a\r\n~1\r\n$5\r\nhello\r\n*1\r\n:1\r\n_\r\n";
+        let mut scan = FrameScan::new();
+        for end in 0..input.len() {
+            assert_eq!(resume(&mut scan, &input[..end]), Ok(None));
+        }
+        assert_eq!(resume(&mut scan, input), Ok(Some(input.len())));
+
+        scan.reset();
+        assert_eq!(resume(&mut scan, b"~1\r\n"), Ok(None));
+        assert_eq!(resume(&mut scan, b"~1\r\n%0\r\n"), Err(Error::InvalidSet));
+        scan.reset();
+        assert_eq!(resume(&mut scan, b"|0\r\n"), Ok(None));
+        assert_eq!(resume(&mut scan, b"|0\r\n|0\r\n"), Err(Error::Unknown));
+        scan.reset();
+        assert_eq!(resume(&mut scan, b"#t\r\n"), Ok(Some(4)));
+    }
+}
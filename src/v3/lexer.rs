@@ -1,27 +1,25 @@
-use std::iter::Peekable;
-
-use lexical::{format::STANDARD, parse_with_options, ParseIntegerOptions};
-use memchr::Memchr;
-
-use crate::Error;
+use crate::common::{parse_decimal, skip_payload, CrlfScanner, Error};
 
 use super::tag::{Tag, TagType};
 
 type ScanResult<T> = Result<T, Error>;
 
+/// Smallest encoding of a frame (`_\r\n`), used to reject lengths that cannot
+/// fit in the input before reserving room for them.
+pub(super) const MIN_FRAME_LEN: usize = 3;
+
 #[derive(Debug)]
 pub(crate) struct Lexer<'a> {
     input: &'a [u8],
-    scanner: Peekable<Memchr<'a>>,
+    scanner: CrlfScanner<'a>,
     last_position: usize,
 }
 
 impl<'a> Lexer<'a> {
     pub(crate) fn new(input: &'a [u8]) -> Self {
-        let scanner = Memchr::new(b'\r', input);
         Self {
             input,
-            scanner: scanner.peekable(),
+            scanner: CrlfScanner::new(input),
             last_position: 0,
         }
     }
@@ -30,14 +28,33 @@ impl<'a> Lexer<'a> {
         self.last_position
     }
 
+    /// Most frames the rest of the input can hold, an upper bound on what an
+    /// aggregate header can honestly declare.
+    pub(crate) fn max_frames(&self) -> usize {
+        (self.input.len() - self.last_position) / MIN_FRAME_LEN
+    }
+
     fn walk(&mut self) -> Option<usize> {
-        let end_position = loop {
-            let end_position = self.scanner.next()?;
-            if let Some(b'\n') = self.input.get(end_position + 1) {
-                break end_position;
+        self.scanner.next()
+    }
+
+    /// Takes a payload by its declared length, so it may contain CRLF, and
+    /// returns where it ends.
+    fn skip_payload(
+        &mut self,
+        start_position: usize,
+        len: usize,
+        error: Error,
+    ) -> Option<ScanResult<usize>> {
+        match skip_payload(self.input, start_position, len, error) {
+            Ok(Some(next)) => {
+                self.last_position = next;
+                self.scanner.seek(next);
+                Some(Ok(next - 2))
             }
-        };
-        Some(end_position)
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
     }
 
     fn match_tag(
@@ -67,37 +84,41 @@ impl<'a> Lexer<'a> {
             }
             b'$' => {
                 let follow = self.input.get(start_position..end_position)?;
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<isize, _, STANDARD>(follow, &options);
-                match len_result {
-                    Ok(-1) => TagType::Null,
+                match parse_decimal::<isize>(follow) {
+                    Ok(-1) => {
+                        self.last_position = end_position + 2;
+                        TagType::Null
+                    }
+                    Ok(len) if len < 0 => return Some(Err(Error::InvalidBulkString)),
                     Ok(len) => {
                         start_position = end_position + 2;
-                        end_position = self.scanner.next()?;
-                        let len = len as usize;
-                        if len > end_position - start_position {
-                            return Some(Err(Error::from(Error::InvalidBulkString)));
-                        }
-
-                        self.last_position = end_position + 2;
+                        end_position = match self.skip_payload(
+                            start_position,
+                            len as usize,
+                            Error::InvalidBulkString,
+                        )? {
+                            Ok(end_position) => end_position,
+                            Err(e) => return Some(Err(e)),
+                        };
                         TagType::BulkString
                     }
-                    Err(e) => return Some(Err(Error::from(e))),
+                    Err(e) => return Some(Err(e)),
                 }
             }
             b'*' => {
                 let follow = self.input.get(start_position..end_position)?;
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<isize, _, STANDARD>(follow, &options);
                 self.last_position = end_position + 2;
-                match len_result {
+                match parse_decimal::<isize>(follow) {
                     Ok(-1) => TagType::Null,
-                    Ok(len) if len < 0 => return Some(Err(Error::from(Error::InvalidArray))),
+                    Ok(len) if len < 0 => return Some(Err(Error::InvalidArray)),
                     Ok(_) => TagType::Array,
-                    Err(e) => return Some(Err(Error::from(e))),
+                    Err(e) => return Some(Err(e)),
                 }
             }
             b'_' => {
+                if start_position != end_position {
+                    return Some(Err(Error::InvalidNull));
+                }
                 self.last_position = end_position + 2;
                 TagType::Null
             }
@@ -115,20 +136,17 @@ impl<'a> Lexer<'a> {
             }
             b'!' => {
                 let follow = self.input.get(start_position..end_position)?;
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<usize, _, STANDARD>(follow, &options);
-                match len_result {
+                match parse_decimal::<usize>(follow) {
                     Ok(len) => {
                         start_position = end_position + 2;
-                        end_position = self.walk()?;
-                        if len > end_position - start_position {
-                            return Some(Err(Error::InvalidError));
-                        } else {
-                            self.last_position = end_position + 2;
-                            TagType::BulkError
-                        }
+                        end_position =
+                            match self.skip_payload(start_position, len, Error::InvalidError)? {
+                                Ok(end_position) => end_position,
+                                Err(e) => return Some(Err(e)),
+                            };
+                        TagType::BulkError
                     }
-                    Err(e) => return Some(Err(Error::from(e))),
+                    Err(e) => return Some(Err(e)),
                 }
             }
             b'~' => {
@@ -141,30 +159,33 @@ impl<'a> Lexer<'a> {
             }
             b'=' => {
                 let follow = self.input.get(start_position..end_position)?;
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<usize, _, STANDARD>(follow, &options);
-                match len_result {
+                match parse_decimal::<usize>(follow) {
+                    // The payload starts with a three byte format and a `:`.
+                    Ok(len) if len < 4 => return Some(Err(Error::InvalidVerbatimString)),
                     Ok(len) => {
                         start_position = end_position + 2;
-                        end_position = self.walk()?;
-                        if len > end_position - start_position {
-                            return Some(Err(Error::InvalidError));
-                        } else {
-                            self.last_position = end_position + 2;
-                            TagType::VerbatimString
+                        end_position = match self.skip_payload(
+                            start_position,
+                            len,
+                            Error::InvalidVerbatimString,
+                        )? {
+                            Ok(end_position) => end_position,
+                            Err(e) => return Some(Err(e)),
+                        };
+                        if self.input[start_position + 3] != b':' {
+                            return Some(Err(Error::InvalidVerbatimString));
                         }
+                        TagType::VerbatimString
                     }
-                    Err(e) => return Some(Err(Error::from(e))),
+                    Err(e) => return Some(Err(e)),
                 }
             }
             b'>' => {
                 let follow = self.input.get(start_position..end_position)?;
-                let options = ParseIntegerOptions::new();
-                let len_result = parse_with_options::<usize, _, STANDARD>(follow, &options);
                 self.last_position = end_position + 2;
-                match len_result {
+                match parse_decimal::<usize>(follow) {
                     Ok(_) => TagType::Push,
-                    Err(e) => return Some(Err(Error::from(e))),
+                    Err(e) => return Some(Err(e)),
                 }
             }
             _ => return Some(Err(Error::Unknown)),
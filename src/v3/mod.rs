@@ -1,13 +1,29 @@
+mod arena;
 mod ast;
+mod big_number;
+mod event;
 mod frame;
+mod frame_len;
 mod lexer;
 mod tag;
+mod tape;
+mod verbatim;
 
+pub use arena::{DecodeArena, Elements, ElementsIter, FrameRef, Pairs, PairsIter};
 pub(super) use ast::Ast;
+pub use big_number::BigNumberBuf;
+pub use event::{Event, EventParser};
 pub use frame::Frame;
 pub(super) use lexer::Lexer;
+pub use tape::{Children, Cursor, NodeKind, Tape};
+pub use verbatim::VerbatimFormat;
 
-use crate::{EncodeWithWriter, Encoder, Error, ParseIter, Parser, Remaining};
+use std::ops::Range;
+
+use crate::{
+    EncodeToBuf, EncodeVectored, EncodeWithWriter, Encoder, Error, FrameLen, FrameScan, Message,
+    ParseIter, Parser, Remaining, VectoredEncoder,
+};
 
 pub struct V3;
 
@@ -30,6 +46,89 @@ impl Remaining for DecodeIter<'_> {
     }
 }
 
+impl<'a> DecodeIter<'a> {
+    /// Splits the stream into server pushes and replies to requests.
+    pub fn messages(self) -> Messages<'a> {
+        Messages { inner: self }
+    }
+
+    /// Pairs every frame with the range of input it was decoded from, so it
+    /// can be forwarded or logged byte for byte.
+    pub fn spanned(self) -> Spanned<'a> {
+        Spanned { inner: self }
+    }
+
+    /// Keeps the text of every double in [`Frame::Double`]'s `raw` field, so
+    /// that encoding the frame again reproduces it exactly.
+    pub fn keep_double_text(mut self) -> Self {
+        self.ast.keep_double_text();
+        self
+    }
+}
+
+pub struct Messages<'a> {
+    inner: DecodeIter<'a>,
+}
+
+impl<'a> Iterator for Messages<'a> {
+    type Item = Result<Message<Frame<'a>>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|result| {
+            result.map(|frame| {
+                if frame.is_push() {
+                    Message::Push(frame)
+                } else {
+                    Message::Reply(frame)
+                }
+            })
+        })
+    }
+}
+
+impl Remaining for Messages<'_> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+}
+
+pub struct Spanned<'a> {
+    inner: DecodeIter<'a>,
+}
+
+impl<'a> Iterator for Spanned<'a> {
+    type Item = Result<(Frame<'a>, Range<usize>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.inner.remaining();
+        let frame = self.inner.next()?;
+        Some(frame.map(|frame| (frame, start..self.inner.remaining())))
+    }
+}
+
+impl Remaining for Spanned<'_> {
+    fn remaining(&self) -> usize {
+        self.inner.remaining()
+    }
+}
+
+impl V3 {
+    /// Returns the encoded length of the first frame in `input` without
+    /// building it, or `None` if the frame is not complete yet.
+    pub fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::frame_len(input)
+    }
+}
+
+impl FrameLen for V3 {
+    fn frame_len(input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::frame_len(input)
+    }
+    fn resume_frame_len(scan: &mut FrameScan, input: &[u8]) -> Result<Option<usize>, Error> {
+        frame_len::resume(scan, input)
+    }
+}
+
 impl Parser for V3 {
     type Frame<'a> = (Frame<'a>, usize);
 
@@ -60,6 +159,25 @@ impl Encoder for V3 {
     }
 }
 
+impl EncodeToBuf for V3 {
+    type Frame<'a> = Frame<'a>;
+
+    fn encode_to_buf<B>(frame: Self::Frame<'_>, buf: &mut B)
+    where
+        B: bytes::BufMut,
+    {
+        frame.encode_to_buf(buf)
+    }
+}
+
+impl EncodeVectored for V3 {
+    type Frame<'a> = Frame<'a>;
+
+    fn encode_vectored<'a>(frame: Self::Frame<'a>, encoder: &mut VectoredEncoder<'a>) {
+        frame.encode_vectored(encoder)
+    }
+}
+
 impl EncodeWithWriter for V3 {
     type Frame<'a> = Frame<'a>;
 
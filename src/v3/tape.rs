@@ -0,0 +1,421 @@
+use std::ops::Range;
+
+use crate::{
+    common::{parse_decimal, parse_double, Error},
+    Parser,
+};
+
+use super::{tag::TagType, Frame, Lexer, V3};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NodeKind {
+    SimpleString,
+    SimpleError,
+    BulkString,
+    BulkError,
+    VerbatimString,
+    Null,
+    Integer,
+    Double,
+    Boolean,
+    BigNumber,
+    Array,
+    Map,
+    Set,
+    Push,
+    Attribute,
+}
+
+impl NodeKind {
+    fn from_tag(tag_type: &TagType) -> Self {
+        match tag_type {
+            TagType::SimpleString => Self::SimpleString,
+            TagType::SimpleError => Self::SimpleError,
+            TagType::BulkString => Self::BulkString,
+            TagType::BulkError => Self::BulkError,
+            TagType::VerbatimString => Self::VerbatimString,
+            TagType::Null => Self::Null,
+            TagType::Integer => Self::Integer,
+            TagType::Double => Self::Double,
+            TagType::Boolean => Self::Boolean,
+            TagType::BigNumber => Self::BigNumber,
+            TagType::Array => Self::Array,
+            TagType::Map => Self::Map,
+            TagType::Set => Self::Set,
+            TagType::Push => Self::Push,
+            TagType::Attribute => Self::Attribute,
+        }
+    }
+
+    pub fn is_aggregate(&self) -> bool {
+        matches!(
+            self,
+            Self::Array | Self::Map | Self::Set | Self::Push | Self::Attribute
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: NodeKind,
+    // The whole encoding of the node, children included.
+    raw: Range<usize>,
+    // The payload, or the length digits of an aggregate.
+    data: Range<usize>,
+    // Elements of an aggregate, keys and values counted separately.
+    children: usize,
+    // Tape index just past the node's subtree.
+    next: usize,
+    attributes: Option<usize>,
+}
+
+/// A frame indexed in a single pass into a flat list of nodes, in the spirit
+/// of simd-json's tape, instead of being built into a [`Frame`] tree.
+///
+/// Nodes are stored in encoding order, and each one knows where its subtree
+/// ends, so skipping a value is a single jump however large it is. Payloads
+/// stay in the input and are only parsed when read through a [`Cursor`].
+///
+/// RESP2 input can be indexed too, as RESP3 is a superset of it.
+#[derive(Debug, Clone, Default)]
+pub struct Tape<'a> {
+    input: &'a [u8],
+    nodes: Vec<Node>,
+}
+
+impl<'a> Tape<'a> {
+    /// Indexes the first frame of `input`, returning the tape and the number
+    /// of bytes the frame took up, or `None` when `input` does not even hold
+    /// its first line.
+    pub fn parse(input: &'a [u8]) -> Result<Option<(Self, usize)>, Error> {
+        let mut tape = Self::default();
+        Ok(tape.parse_into(input)?.map(|consumed| (tape, consumed)))
+    }
+
+    /// Like [`Tape::parse`], but reuses this tape's allocation.
+    pub fn parse_into(&mut self, input: &'a [u8]) -> Result<Option<usize>, Error> {
+        self.input = input;
+        self.nodes.clear();
+
+        let mut lexer = Lexer::new(input);
+        // Open aggregates, as tape indices with the elements they still expect.
+        let mut stack: Vec<(usize, usize)> = Vec::new();
+        let mut attributes = None;
+        let mut position = 0;
+
+        loop {
+            let tag = match lexer.next() {
+                Some(tag) => tag?,
+                None if self.nodes.is_empty() => return Ok(None),
+                None => return Err(Error::NotComplete),
+            };
+            let kind = NodeKind::from_tag(&tag.tag_type);
+            let index = self.nodes.len();
+            let children = if kind.is_aggregate() {
+                let len = input
+                    .get(tag.start_position..tag.end_position)
+                    .ok_or(Error::NotComplete)?;
+                let len = parse_decimal::<usize>(len)?;
+                match kind {
+                    NodeKind::Map | NodeKind::Attribute => {
+                        len.checked_mul(2).ok_or(Error::InvalidMap)?
+                    }
+                    _ => len,
+                }
+            } else {
+                0
+            };
+            self.nodes.push(Node {
+                kind,
+                raw: position..lexer.remaining(),
+                data: tag.start_position..tag.end_position,
+                children,
+                next: index + 1,
+                attributes: if kind == NodeKind::Attribute {
+                    None
+                } else {
+                    attributes.take()
+                },
+            });
+            position = lexer.remaining();
+
+            if kind.is_aggregate() {
+                stack.push((index, children));
+            } else {
+                Self::complete(&mut stack);
+            }
+
+            // Close every aggregate whose last element was just read.
+            while let Some(&(open, 0)) = stack.last() {
+                stack.pop();
+                let next = self.nodes.len();
+                let node = &mut self.nodes[open];
+                node.raw.end = position;
+                node.next = next;
+                if self.nodes[open].kind == NodeKind::Attribute {
+                    attributes = Some(open);
+                } else {
+                    Self::complete(&mut stack);
+                }
+            }
+
+            if stack.is_empty() && attributes.is_none() {
+                return Ok(Some(position));
+            }
+        }
+    }
+
+    fn complete(stack: &mut [(usize, usize)]) {
+        if let Some((_, remaining)) = stack.last_mut() {
+            *remaining -= 1;
+        }
+    }
+
+    pub fn input(&self) -> &'a [u8] {
+        self.input
+    }
+
+    /// Number of nodes on the tape, attributes included.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The frame itself, skipping any attributes that precede it.
+    pub fn root(&self) -> Option<Cursor<'_, 'a>> {
+        Cursor {
+            tape: self,
+            index: 0,
+            end: self.nodes.len(),
+        }
+        .value()
+    }
+}
+
+/// A position on a [`Tape`], bounded by the aggregate it belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct Cursor<'t, 'a> {
+    tape: &'t Tape<'a>,
+    index: usize,
+    // Tape index just past the parent's subtree.
+    end: usize,
+}
+
+impl<'t, 'a> Cursor<'t, 'a> {
+    fn node(&self) -> &'t Node {
+        &self.tape.nodes[self.index]
+    }
+
+    /// Steps over attribute nodes to the value they annotate.
+    fn value(mut self) -> Option<Self> {
+        while self.index < self.end {
+            if self.node().kind != NodeKind::Attribute {
+                return Some(self);
+            }
+            self.index = self.node().next;
+        }
+        None
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.node().kind
+    }
+
+    /// Number of elements of an aggregate, counting map keys and values
+    /// separately, or zero for other nodes.
+    pub fn len(&self) -> usize {
+        self.node().children
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `index`-th element of an aggregate, in encoding order. For maps,
+    /// keys sit at even and values at odd indices.
+    pub fn get(&self, index: usize) -> Option<Self> {
+        self.children().nth(index)
+    }
+
+    pub fn children(&self) -> Children<'t, 'a> {
+        let node = self.node();
+        let first = Self {
+            tape: self.tape,
+            index: self.index + 1,
+            end: node.next,
+        };
+        Children {
+            next: if node.kind.is_aggregate() {
+                first.value()
+            } else {
+                None
+            },
+        }
+    }
+
+    /// The next value in the same aggregate, jumping over this node's
+    /// subtree.
+    pub fn skip(&self) -> Option<Self> {
+        Self {
+            tape: self.tape,
+            index: self.node().next,
+            end: self.end,
+        }
+        .value()
+    }
+
+    /// The attributes annotating this value, as an aggregate of alternating
+    /// keys and values.
+    pub fn attributes(&self) -> Option<Self> {
+        self.node().attributes.map(|index| Self {
+            tape: self.tape,
+            index,
+            end: self.tape.nodes[index].next,
+        })
+    }
+
+    /// The range of input covered by this node, children included.
+    pub fn span(&self) -> Range<usize> {
+        self.node().raw.clone()
+    }
+
+    /// The encoding of this node, children included.
+    pub fn raw_bytes(&self) -> &'a [u8] {
+        &self.tape.input[self.node().raw.clone()]
+    }
+
+    /// The payload of a string, error or big number, or the text of an
+    /// integer, double or boolean.
+    pub fn data(&self) -> Option<&'a [u8]> {
+        let node = self.node();
+        match node.kind {
+            _ if node.kind.is_aggregate() => None,
+            NodeKind::Null => None,
+            NodeKind::VerbatimString => self.tape.input.get(node.data.start + 4..node.data.end),
+            _ => self.tape.input.get(node.data.clone()),
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.kind() {
+            NodeKind::SimpleString | NodeKind::BulkString | NodeKind::VerbatimString => self.data(),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self.kind() {
+            NodeKind::Integer => parse_decimal::<i64>(self.data()?).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_double(&self) -> Option<f64> {
+        match self.kind() {
+            NodeKind::Double => parse_double(self.data()?).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_boolean(&self) -> Option<bool> {
+        match (self.kind(), self.data()?) {
+            (NodeKind::Boolean, b"t") => Some(true),
+            (NodeKind::Boolean, b"f") => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.kind() == NodeKind::Null
+    }
+
+    /// Materializes this node, attributes included, as a [`Frame`].
+    pub fn to_frame(&self) -> Result<Frame<'a>, Error> {
+        let start = match self.node().attributes {
+            Some(index) => self.tape.nodes[index].raw.start,
+            None => self.node().raw.start,
+        };
+        let raw = &self.tape.input[start..self.node().raw.end];
+        V3::parse(raw)?
+            .map(|(frame, _)| frame)
+            .ok_or(Error::NotComplete)
+    }
+}
+
+/// Iterator over the elements of an aggregate.
+#[derive(Debug, Clone)]
+pub struct Children<'t, 'a> {
+    next: Option<Cursor<'t, 'a>>,
+}
+
+impl<'t, 'a> Iterator for Children<'t, 'a> {
+    type Item = Cursor<'t, 'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next?;
+        self.next = current.skip();
+        Some(current)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tape() {
+        let input =
+            b"*4\r\n$3\r\nfoo\r\n%1\r\n+k\r\n*2\r\n:1\r\n:2\r\n|1\r\n+ttl\r\n:3\r\n#t\r\n,1.5\r\n+next\r\n";
+        let (tape, consumed) = Tape::parse(input).unwrap().unwrap();
+        assert_eq!(consumed, input.len() - 7);
+        assert_eq!(tape.len(), 12);
+
+        let root = tape.root().unwrap();
+        assert_eq!(root.kind(), NodeKind::Array);
+        assert_eq!(root.len(), 4);
+        assert_eq!(root.raw_bytes(), &input[..consumed]);
+        assert_eq!(root.get(0).unwrap().as_bytes(), Some(&b"foo"[..]));
+
+        let map = root.get(1).unwrap();
+        assert_eq!(map.kind(), NodeKind::Map);
+        assert_eq!(map.raw_bytes(), b"%1\r\n+k\r\n*2\r\n:1\r\n:2\r\n");
+        assert_eq!(map.span(), 13..33);
+        assert_eq!(root.span(), 0..consumed);
+        assert_eq!(map.get(1).unwrap().get(1).unwrap().as_integer(), Some(2));
+        assert_eq!(map.skip().unwrap().as_boolean(), Some(true));
+
+        let boolean = root.get(2).unwrap();
+        let attributes = boolean.attributes().unwrap();
+        assert_eq!(attributes.kind(), NodeKind::Attribute);
+        assert_eq!(attributes.get(0).unwrap().as_bytes(), Some(&b"ttl"[..]));
+        assert_eq!(
+            boolean.to_frame().unwrap(),
+            V3::parse(b"|1\r\n+ttl\r\n:3\r\n#t\r\n").unwrap().unwrap().0
+        );
+
+        assert_eq!(root.get(3).unwrap().as_double(), Some(1.5));
+        assert!(root.get(3).unwrap().skip().is_none());
+        assert!(root.get(4).is_none());
+        assert_eq!(root.children().count(), 4);
+    }
+
+    #[test]
+    fn test_tape_incomplete() {
+        assert!(Tape::parse(b"").unwrap().is_none());
+        assert_eq!(
+            Tape::parse(b"*2\r\n:1\r\n").unwrap_err(),
+            Error::NotComplete
+        );
+
+        let mut tape = Tape::default();
+        assert_eq!(tape.parse_into(b"*0\r\n").unwrap(), Some(4));
+        let root = tape.root().unwrap();
+        assert!(root.is_empty());
+        assert!(root.get(0).is_none());
+        assert_eq!(tape.parse_into(b"_\r\n").unwrap(), Some(3));
+        assert!(tape.root().unwrap().is_null());
+    }
+}
@@ -0,0 +1,54 @@
+/// The format a verbatim string is sent in, from the three bytes ahead of
+/// its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerbatimFormat {
+    /// `txt`, plain text.
+    Text,
+    /// `mkd`, markdown.
+    Markdown,
+    Other([u8; 3]),
+}
+
+impl VerbatimFormat {
+    pub fn as_bytes(&self) -> &[u8; 3] {
+        match self {
+            VerbatimFormat::Text => b"txt",
+            VerbatimFormat::Markdown => b"mkd",
+            VerbatimFormat::Other(format) => format,
+        }
+    }
+}
+
+impl From<[u8; 3]> for VerbatimFormat {
+    fn from(format: [u8; 3]) -> Self {
+        match &format {
+            b"txt" => VerbatimFormat::Text,
+            b"mkd" => VerbatimFormat::Markdown,
+            _ => VerbatimFormat::Other(format),
+        }
+    }
+}
+
+impl From<VerbatimFormat> for [u8; 3] {
+    fn from(format: VerbatimFormat) -> Self {
+        *format.as_bytes()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_verbatim_format() {
+        assert_eq!(VerbatimFormat::from(*b"txt"), VerbatimFormat::Text);
+        assert_eq!(VerbatimFormat::from(*b"mkd"), VerbatimFormat::Markdown);
+        assert_eq!(
+            VerbatimFormat::from(*b"csv"),
+            VerbatimFormat::Other(*b"csv")
+        );
+        for format in [*b"txt", *b"mkd", *b"csv"] {
+            assert_eq!(<[u8; 3]>::from(VerbatimFormat::from(format)), format);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Decoding replies straight into user types, in the manner of serde, without
+//! building a [`Frame`](crate::v3::Frame) tree first.
+//!
+//! A [`Visitor`] is handed each value as it is parsed. Aggregates come with a
+//! [`SeqAccess`] or [`MapAccess`] through which the visitor pulls its
+//! elements, each with a visitor of its own. Elements a visitor leaves unread
+//! are skipped, and attributes are skipped altogether.
+
+use crate::{
+    common::{Error, Remaining},
+    v2::{Event as V2Event, EventParser as V2EventParser, V2},
+    v3::{Event, EventParser, V3},
+};
+
+/// Receives one decoded value. Every method rejects the value by default,
+/// except that simple strings fall back to `visit_bulk` and sets and pushes
+/// fall back to `visit_array`.
+pub trait Visitor<'de>: Sized {
+    type Value;
+
+    fn visit_simple_string(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        self.visit_bulk(data)
+    }
+
+    fn visit_simple_error(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_bulk(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_bulk_error(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_verbatim_string(self, format: [u8; 3], data: &'de [u8]) -> Result<Self::Value, Error> {
+        let _ = (format, data);
+        Err(Error::InvalidType)
+    }
+
+    fn visit_null(self) -> Result<Self::Value, Error> {
+        Err(Error::InvalidType)
+    }
+
+    fn visit_integer(self, data: i64) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_double(self, data: f64) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_boolean(self, data: bool) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_big_number(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        let _ = data;
+        Err(Error::InvalidType)
+    }
+
+    fn visit_array<A>(self, len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let _ = (len, seq);
+        Err(Error::InvalidType)
+    }
+
+    fn visit_set<A>(self, len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visit_array(len, seq)
+    }
+
+    fn visit_push<A>(self, len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visit_array(len, seq)
+    }
+
+    fn visit_map<A>(self, len: usize, map: &mut A) -> Result<Self::Value, Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let _ = (len, map);
+        Err(Error::InvalidType)
+    }
+}
+
+pub trait SeqAccess<'de> {
+    /// Decodes the next element with `visitor`, or returns `None` once all
+    /// elements were read.
+    fn next_element<V>(&mut self, visitor: V) -> Result<Option<V::Value>, Error>
+    where
+        V: Visitor<'de>;
+
+    /// Elements not read yet.
+    fn remaining(&self) -> usize;
+}
+
+pub trait MapAccess<'de> {
+    /// Decodes the next key with `visitor`, or returns `None` once all
+    /// entries were read.
+    fn next_key<V>(&mut self, visitor: V) -> Result<Option<V::Value>, Error>
+    where
+        V: Visitor<'de>;
+
+    /// Decodes the value of the key just read.
+    fn next_value<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>;
+
+    /// Entries not read yet.
+    fn remaining(&self) -> usize;
+}
+
+/// Protocols whose replies can be decoded with a [`Visitor`].
+pub trait Visit {
+    /// Decodes the first value of `input` with `visitor`, returning it with
+    /// the number of bytes it took up, or `None` when `input` does not even
+    /// hold its first line.
+    fn visit<'de, V>(input: &'de [u8], visitor: V) -> Result<Option<(V::Value, usize)>, Error>
+    where
+        V: Visitor<'de>;
+}
+
+pub fn visit<'de, P, V>(input: &'de [u8], visitor: V) -> Result<Option<(V::Value, usize)>, Error>
+where
+    P: Visit,
+    V: Visitor<'de>,
+{
+    P::visit(input, visitor)
+}
+
+/// The event parsers of both protocols, with RESP2 events expressed as their
+/// RESP3 counterparts.
+trait EventSource<'de>: Remaining {
+    fn next_event(&mut self) -> Option<Result<Event<'de>, Error>>;
+
+    fn skip_value(&mut self) -> Result<(), Error>;
+}
+
+impl<'de> EventSource<'de> for EventParser<'de> {
+    fn next_event(&mut self) -> Option<Result<Event<'de>, Error>> {
+        self.next()
+    }
+
+    fn skip_value(&mut self) -> Result<(), Error> {
+        EventParser::skip_value(self)
+    }
+}
+
+impl<'de> EventSource<'de> for V2EventParser<'de> {
+    fn next_event(&mut self) -> Option<Result<Event<'de>, Error>> {
+        self.next().map(|event| {
+            event.map(|event| match event {
+                V2Event::SimpleString(data) => Event::SimpleString(data),
+                V2Event::SimpleError(data) => Event::SimpleError(data),
+                V2Event::Bulk(data) => Event::Bulk(data),
+                V2Event::Null => Event::Null,
+                V2Event::Integer(data) => Event::Integer(data),
+                V2Event::ArrayStart(len) => Event::ArrayStart(len),
+                V2Event::End => Event::End,
+            })
+        })
+    }
+
+    fn skip_value(&mut self) -> Result<(), Error> {
+        V2EventParser::skip_value(self)
+    }
+}
+
+fn next_event<'de, S>(source: &mut S) -> Result<Event<'de>, Error>
+where
+    S: EventSource<'de>,
+{
+    source.next_event().unwrap_or(Err(Error::NotComplete))
+}
+
+fn visit_value<'de, S, V>(source: &mut S, visitor: V) -> Result<V::Value, Error>
+where
+    S: EventSource<'de>,
+    V: Visitor<'de>,
+{
+    let mut event = next_event(source)?;
+    while let Event::AttributeStart(len) = event {
+        for _ in 0..len * 2 {
+            source.skip_value()?;
+        }
+        next_event(source)?;
+        event = next_event(source)?;
+    }
+
+    match event {
+        Event::SimpleString(data) => visitor.visit_simple_string(data),
+        Event::SimpleError(data) => visitor.visit_simple_error(data),
+        Event::Bulk(data) => visitor.visit_bulk(data),
+        Event::BulkError(data) => visitor.visit_bulk_error(data),
+        Event::VerbatimString { format, data } => visitor.visit_verbatim_string(format, data),
+        Event::Null => visitor.visit_null(),
+        Event::Integer(data) => visitor.visit_integer(data),
+        Event::Double(data) => visitor.visit_double(data),
+        Event::Boolean(data) => visitor.visit_boolean(data),
+        Event::BigNumber(data) => visitor.visit_big_number(data),
+        Event::ArrayStart(len) => Seq::visit(source, len, |seq| visitor.visit_array(len, seq)),
+        Event::SetStart(len) => Seq::visit(source, len, |seq| visitor.visit_set(len, seq)),
+        Event::PushStart(len) => Seq::visit(source, len, |seq| visitor.visit_push(len, seq)),
+        Event::MapStart(len) => {
+            let mut map = Map {
+                seq: Seq {
+                    source,
+                    remaining: len * 2,
+                },
+            };
+            let value = visitor.visit_map(len, &mut map)?;
+            map.seq.finish()?;
+            Ok(value)
+        }
+        Event::AttributeStart(_) | Event::End => Err(Error::InvalidType),
+    }
+}
+
+struct Seq<'a, S> {
+    source: &'a mut S,
+    remaining: usize,
+}
+
+impl<'a, 'de, S> Seq<'a, S>
+where
+    S: EventSource<'de>,
+{
+    fn visit<T, F>(source: &'a mut S, len: usize, visit: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Error>,
+    {
+        let mut seq = Self {
+            source,
+            remaining: len,
+        };
+        let value = visit(&mut seq)?;
+        seq.finish()?;
+        Ok(value)
+    }
+
+    /// Skips the elements the visitor left unread, then the aggregate's end.
+    fn finish(self) -> Result<(), Error> {
+        for _ in 0..self.remaining {
+            self.source.skip_value()?;
+        }
+        match next_event(self.source)? {
+            Event::End => Ok(()),
+            _ => Err(Error::InvalidType),
+        }
+    }
+}
+
+impl<'a, 'de, S> SeqAccess<'de> for Seq<'a, S>
+where
+    S: EventSource<'de>,
+{
+    fn next_element<V>(&mut self, visitor: V) -> Result<Option<V::Value>, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        visit_value(self.source, visitor).map(Some)
+    }
+
+    fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+struct Map<'a, S> {
+    // Counts keys and values separately.
+    seq: Seq<'a, S>,
+}
+
+impl<'a, 'de, S> MapAccess<'de> for Map<'a, S>
+where
+    S: EventSource<'de>,
+{
+    fn next_key<V>(&mut self, visitor: V) -> Result<Option<V::Value>, Error>
+    where
+        V: Visitor<'de>,
+    {
+        // Skip the value of a key whose value was never read.
+        if !self.seq.remaining.is_multiple_of(2) {
+            self.seq.remaining -= 1;
+            self.seq.source.skip_value()?;
+        }
+        self.seq.next_element(visitor)
+    }
+
+    fn next_value<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.seq.remaining.is_multiple_of(2) {
+            return Err(Error::InvalidMap);
+        }
+        self.seq.next_element(visitor)?.ok_or(Error::InvalidMap)
+    }
+
+    fn remaining(&self) -> usize {
+        self.seq.remaining / 2
+    }
+}
+
+fn visit_source<'de, S, V>(mut source: S, visitor: V) -> Result<Option<(V::Value, usize)>, Error>
+where
+    S: EventSource<'de>,
+    V: Visitor<'de>,
+{
+    let first = match source.next_event() {
+        Some(event) => event?,
+        None => return Ok(None),
+    };
+    let mut source = Replay {
+        first: Some(first),
+        source,
+    };
+    let value = visit_value(&mut source, visitor)?;
+    Ok(Some((value, source.remaining())))
+}
+
+/// Hands out an event that was already pulled to check for empty input.
+struct Replay<'de, S> {
+    first: Option<Event<'de>>,
+    source: S,
+}
+
+impl<'de, S> Remaining for Replay<'de, S>
+where
+    S: EventSource<'de>,
+{
+    fn remaining(&self) -> usize {
+        self.source.remaining()
+    }
+}
+
+impl<'de, S> EventSource<'de> for Replay<'de, S>
+where
+    S: EventSource<'de>,
+{
+    fn next_event(&mut self) -> Option<Result<Event<'de>, Error>> {
+        match self.first.take() {
+            Some(event) => Some(Ok(event)),
+            None => self.source.next_event(),
+        }
+    }
+
+    fn skip_value(&mut self) -> Result<(), Error> {
+        self.source.skip_value()
+    }
+}
+
+impl Visit for V2 {
+    fn visit<'de, V>(input: &'de [u8], visitor: V) -> Result<Option<(V::Value, usize)>, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visit_source(V2EventParser::new(input), visitor)
+    }
+}
+
+impl Visit for V3 {
+    fn visit<'de, V>(input: &'de [u8], visitor: V) -> Result<Option<(V::Value, usize)>, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visit_source(EventParser::new(input), visitor)
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use minivec::mini_vec;
+use rresp::{
+    decode,
+    mock::{MockServer, Protocol, Reply},
+    v2::{Frame as V2Frame, V2},
+    v3::{Frame as V3Frame, V3},
+    Invalidation,
+};
+
+fn command(args: &[&'static [u8]]) -> Vec<u8> {
+    V2Frame::Array(args.iter().map(|arg| V2Frame::BulkString(arg)).collect()).encode()
+}
+
+fn read_until_complete<R: Read>(reader: &mut R, buf: &mut Vec<u8>) -> usize {
+    let mut chunk = [0u8; 64];
+    loop {
+        if let Ok(Some((_, consumed))) = decode::<V3>(buf) {
+            return consumed;
+        }
+        let read = reader.read(&mut chunk).unwrap();
+        assert_ne!(read, 0, "connection closed before a complete frame");
+        buf.extend_from_slice(&chunk[..read]);
+    }
+}
+
+#[test]
+fn mock_duplex() {
+    let mut stream = MockServer::new(Protocol::V2)
+        .expect(&[b"GET", b"foo"], Reply::v2(&V2Frame::BulkString(b"bar")))
+        .expect(&[b"INCR", b"n"], Reply::v2(&V2Frame::Integer(1)))
+        .partial_writes(3)
+        .duplex();
+
+    stream.write_all(&command(&[b"GET", b"foo"])).unwrap();
+    let mut buf = Vec::new();
+    let mut out = [0u8; 64];
+    while buf.len() < 9 {
+        let read = stream.read(&mut out).unwrap();
+        assert!(read <= 3);
+        buf.extend_from_slice(&out[..read]);
+    }
+    assert_eq!(
+        decode::<V2>(&buf).unwrap().unwrap(),
+        (V2Frame::BulkString(b"bar"), 9)
+    );
+
+    let request = command(&[b"INCR", b"n"]);
+    let (head, tail) = request.split_at(5);
+    stream.write_all(head).unwrap();
+    stream.write_all(tail).unwrap();
+    stream.finish().unwrap();
+}
+
+#[test]
+fn mock_unexpected_command() {
+    let mut stream = MockServer::new(Protocol::V2)
+        .expect(&[b"PING"], Reply::raw(b"+PONG\r\n".to_vec()))
+        .duplex();
+
+    stream.write_all(&command(&[b"GET", b"foo"])).unwrap();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).unwrap();
+    let (frame, _) = decode::<V2>(&buf).unwrap().unwrap();
+    assert!(frame.as_error().is_some());
+    assert!(stream.finish().is_err());
+}
+
+#[test]
+fn mock_tcp() {
+    let push = V3Frame::Push {
+        data: mini_vec![
+            V3Frame::BulkString {
+                data: b"invalidate",
+                attributes: None,
+            },
+            V3Frame::Array {
+                data: mini_vec![V3Frame::BulkString {
+                    data: b"foo",
+                    attributes: None,
+                }],
+                attributes: None,
+            },
+        ],
+        attributes: None,
+    };
+    let server = MockServer::new(Protocol::V3)
+        .expect(
+            &[b"SET", b"foo", b"bar"],
+            Reply::v3(&V3Frame::SimpleString {
+                data: b"OK",
+                attributes: None,
+            }),
+        )
+        .push(Reply::v3(&push))
+        .partial_writes(4)
+        .spawn()
+        .unwrap();
+
+    let mut stream = TcpStream::connect(server.addr()).unwrap();
+    stream
+        .write_all(&command(&[b"SET", b"foo", b"bar"]))
+        .unwrap();
+
+    let mut buf = Vec::new();
+    let consumed = read_until_complete(&mut stream, &mut buf);
+    let (frame, _) = decode::<V3>(&buf).unwrap().unwrap();
+    assert_eq!(frame.as_bytes(), Some(&b"OK"[..]));
+    drop(frame);
+
+    buf.drain(..consumed);
+    read_until_complete(&mut stream, &mut buf);
+    let (frame, _) = decode::<V3>(&buf).unwrap().unwrap();
+    assert_eq!(
+        frame.as_invalidation(),
+        Some(Invalidation::Keys(vec![&b"foo"[..]]))
+    );
+
+    drop(stream);
+    server.join().unwrap();
+}
@@ -1,8 +1,9 @@
 use minivec::mini_vec;
 use rresp::{
     decode,
-    v2::{Frame, V2},
-    Error,
+    v2::{DecodeArena, Decoder, Frame, V2},
+    visit::{visit, SeqAccess, Visitor},
+    Error, FrameReader, Invalidation, Message, ParseIter, Redirect, RedisErrorKind, MAX_DEPTH,
 };
 
 #[test]
@@ -56,3 +57,443 @@ fn decode_v2() {
     let (frame, remaining) = decode::<V2>(input.as_slice()).unwrap().unwrap();
     assert_eq!((frame, remaining), (Frame::Integer(-1), 5));
 }
+
+#[test]
+fn decode_v2_invalidation() {
+    let input = b"*3\r\n$7\r\nmessage\r\n$20\r\n__redis__:invalidate\r\n*1\r\n$3\r\nfoo\r\n";
+    let (frame, _) = decode::<V2>(input.as_slice()).unwrap().unwrap();
+    assert!(frame.is_push());
+    assert_eq!(
+        frame.as_invalidation(),
+        Some(Invalidation::Keys(vec![b"foo".as_slice()]))
+    );
+
+    let input = b"*3\r\n$7\r\nmessage\r\n$20\r\n__redis__:invalidate\r\n$-1\r\n";
+    let (frame, _) = decode::<V2>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(frame.as_invalidation(), Some(Invalidation::Flush));
+
+    let input = b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n";
+    let (frame, _) = decode::<V2>(input.as_slice()).unwrap().unwrap();
+    assert!(frame.is_push());
+    assert_eq!(frame.as_invalidation(), None);
+}
+
+#[test]
+fn decode_v2_messages() {
+    let input = b"+OK\r\n*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n";
+    let messages = V2::parse_iter(input.as_slice())
+        .messages()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        messages,
+        vec![
+            Message::Reply(Frame::SimpleString(b"OK")),
+            Message::Push(Frame::Array(mini_vec![
+                Frame::BulkString(b"message"),
+                Frame::BulkString(b"news"),
+                Frame::BulkString(b"hello"),
+            ])),
+        ]
+    );
+
+    let pushes: [&[u8]; 4] = [
+        b"*3\r\n$8\r\nsmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n",
+        b"*4\r\n$8\r\npmessage\r\n$2\r\nn*\r\n$4\r\nnews\r\n$5\r\nhello\r\n",
+        b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n:1\r\n",
+        b"*3\r\n$11\r\nunsubscribe\r\n$-1\r\n:0\r\n",
+    ];
+    for input in pushes {
+        let (frame, _) = decode::<V2>(input).unwrap().unwrap();
+        assert!(frame.is_push(), "{:?}", frame);
+    }
+
+    // Replies that merely start like a pub/sub message, such as an `LRANGE`
+    // of a list whose first element is "message", stay replies.
+    let replies: [&[u8]; 5] = [
+        b"*2\r\n$7\r\nmessage\r\n$4\r\nnews\r\n",
+        b"*4\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n$3\r\nbye\r\n",
+        b"*3\r\n$9\r\nsubscribe\r\n$4\r\nnews\r\n$1\r\n1\r\n",
+        b"*3\r\n$8\r\npmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n",
+        b"*3\r\n$7\r\nmessage\r\n:1\r\n$5\r\nhello\r\n",
+    ];
+    for input in replies {
+        let messages = V2::parse_iter(input)
+            .messages()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert!(
+            matches!(messages[..], [Message::Reply(_)]),
+            "{:?}",
+            messages
+        );
+    }
+}
+
+#[test]
+fn decode_v2_error() {
+    let input = b"-ASK 3999 127.0.0.1:6381\r\n";
+    let (frame, _) = decode::<V2>(input.as_slice()).unwrap().unwrap();
+    let error = frame.as_error().unwrap();
+    assert_eq!(
+        error.kind(),
+        RedisErrorKind::Ask(Redirect {
+            slot: 3999,
+            host: b"127.0.0.1",
+            port: 6381,
+        })
+    );
+
+    let input = b"-CLUSTERDOWN The cluster is down\r\n";
+    let (frame, _) = decode::<V2>(input.as_slice()).unwrap().unwrap();
+    assert!(frame.as_error().unwrap().is_retriable());
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl std::io::Read for ByteReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match (self.0.split_first(), buf.first_mut()) {
+            (Some((byte, rest)), Some(slot)) => {
+                *slot = *byte;
+                self.0 = rest;
+                Ok(1)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+#[test]
+fn decode_v2_frame_reader() {
+    let input = b"+OK\r\n*2\r\n$5\r\nhello\r\n:42\r\n$-1\r\n";
+    let mut reader = FrameReader::<_, V2>::with_capacity(4, ByteReader(input));
+
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((Frame::SimpleString(b"OK"), 5))
+    );
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((
+            Frame::Array(mini_vec![Frame::BulkString(b"hello"), Frame::Integer(42)]),
+            20
+        ))
+    );
+    assert_eq!(reader.read_frame().unwrap(), Some((Frame::Null, 5)));
+    assert_eq!(reader.read_frame().unwrap(), None);
+
+    let mut reader = FrameReader::<_, V2>::new(b"*2\r\n$5\r\nhel".as_slice());
+    assert_eq!(
+        reader.read_frame().unwrap_err().kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+
+    let mut reader = FrameReader::<_, V2>::new(b"?\r\n".as_slice());
+    assert_eq!(
+        reader.read_frame().unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+
+    // A frame trickling in a byte at a time is measured once, not once per
+    // read, so this stays linear in its length.
+    let mut input = b"*20000\r\n".to_vec();
+    input.extend_from_slice(&b":1\r\n".repeat(20_000));
+    input.extend_from_slice(b"+OK\r\n");
+    let mut reader = FrameReader::<_, V2>::new(ByteReader(&input));
+    match reader.read_frame().unwrap() {
+        Some((Frame::Array(data), len)) => {
+            assert_eq!(data.len(), 20_000);
+            assert_eq!(len, input.len() - 5);
+        }
+        frame => panic!("unexpected frame {frame:?}"),
+    }
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((Frame::SimpleString(b"OK"), 5))
+    );
+}
+
+#[test]
+fn decode_v2_read_bulk() {
+    let payload = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let mut input = Frame::BulkString(&payload).encode();
+    input.extend_from_slice(b"$-1\r\n$0\r\n\r\n+OK\r\n");
+
+    let mut reader = FrameReader::<_, V2>::with_capacity(64, input.as_slice());
+    let mut sink = Vec::new();
+    assert_eq!(
+        reader.read_bulk(&mut sink).unwrap(),
+        Some(payload.len() as u64)
+    );
+    assert_eq!(sink, payload);
+
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), None);
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), Some(0));
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((Frame::SimpleString(b"OK"), 5))
+    );
+
+    let mut reader = FrameReader::<_, V2>::new(ByteReader(b"$5\r\nhello\r\n:1\r\n"));
+    let mut sink = Vec::new();
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), Some(5));
+    assert_eq!(sink, b"hello");
+    assert_eq!(reader.read_frame().unwrap(), Some((Frame::Integer(1), 4)));
+
+    let mut reader = FrameReader::<_, V2>::new(b"$5\r\nhel".as_slice());
+    assert_eq!(
+        reader.read_bulk(&mut sink).unwrap_err().kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+
+    let mut reader = FrameReader::<_, V2>::new(b":1\r\n".as_slice());
+    assert_eq!(
+        reader.read_bulk(&mut sink).unwrap_err().kind(),
+        std::io::ErrorKind::InvalidData
+    );
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Option<&'de [u8]>;
+
+    fn visit_bulk(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        Ok(Some(data))
+    }
+
+    fn visit_null(self) -> Result<Self::Value, Error> {
+        Ok(None)
+    }
+}
+
+struct VecVisitor;
+
+impl<'de> Visitor<'de> for VecVisitor {
+    type Value = Vec<Option<&'de [u8]>>;
+
+    fn visit_array<A>(self, len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(len);
+        while let Some(value) = seq.next_element(BytesVisitor)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+#[test]
+fn decode_v2_visit() {
+    let input = b"*3\r\n$3\r\nfoo\r\n$-1\r\n$3\r\nbar\r\n";
+    assert_eq!(
+        visit::<V2, _>(input, VecVisitor),
+        Ok(Some((
+            vec![Some(&b"foo"[..]), None, Some(b"bar")],
+            input.len()
+        )))
+    );
+    assert_eq!(
+        visit::<V2, _>(b":1\r\n", VecVisitor),
+        Err(Error::InvalidType)
+    );
+}
+
+#[test]
+fn decode_v2_spanned() {
+    let input = b"+OK\r\n*2\r\n$5\r\nhello\r\n:42\r\n$-1\r\n";
+    let frames = V2::parse_iter(input.as_slice())
+        .spanned()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(
+        frames,
+        vec![
+            (Frame::SimpleString(b"OK"), 0..5),
+            (
+                Frame::Array(mini_vec![Frame::BulkString(b"hello"), Frame::Integer(42)]),
+                5..25
+            ),
+            (Frame::Null, 25..30),
+        ]
+    );
+    for (frame, span) in frames {
+        assert_eq!(frame.encode(), &input[span]);
+    }
+}
+
+/// What [`decode`] makes of `input`, in the terms `frame_len` reports.
+fn decoded_len(input: &[u8]) -> Result<Option<usize>, Error> {
+    match decode::<V2>(input) {
+        Ok(frame) => Ok(frame.map(|(_, len)| len)),
+        Err(Error::NotComplete) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[test]
+fn decode_v2_frame_len() {
+    let inputs: [&[u8]; 6] = [
+        b"+OK\r\n",
+        b"$5\r\nhello\r\n",
+        b"$-1\r\n",
+        b"*6\r\n:10\r\n:-1\r\n$5\r\nhello\r\n+world\r\n-err\r\n*1\r\n+ok\r\n",
+        b"*3\r\n$7\r\nmessage\r\n$4\r\nnews\r\n$5\r\nhello\r\n",
+        b"*3\r\n*0\r\n*1\r\n*1\r\n$-1\r\n:1\r\n",
+    ];
+    for input in inputs {
+        let (_, len) = decode::<V2>(input).unwrap().unwrap();
+        assert_eq!(V2::frame_len(input), Ok(Some(len)));
+        for end in 0..input.len() {
+            assert_eq!(V2::frame_len(&input[..end]), Ok(None));
+        }
+    }
+    assert_eq!(V2::frame_len(b"*1\r\n?\r\n"), Err(Error::Unknown));
+
+    // Both accept and refuse the same frames, for the same reasons.
+    let mut deep = b"*1\r\n".repeat(MAX_DEPTH);
+    deep.extend_from_slice(b"*0\r\n");
+    let invalid: [&[u8]; 6] = [
+        b"*-1\r\n",
+        b"*2\r\n:1\r\n*-1\r\n",
+        b"$-2\r\n",
+        b"$3\r\nabcd\r\n",
+        b"*1\r\n:x\r\n",
+        &deep,
+    ];
+    for input in inputs.iter().chain(&invalid) {
+        for end in 0..=input.len() {
+            assert_eq!(
+                V2::frame_len(&input[..end]),
+                decoded_len(&input[..end]),
+                "{:?}",
+                String::from_utf8_lossy(&input[..end])
+            );
+        }
+    }
+    for input in invalid {
+        assert!(V2::frame_len(input).is_err());
+    }
+}
+
+#[test]
+fn decode_v2_deep_nesting() {
+    let mut input = b"*1\r\n".repeat(MAX_DEPTH);
+    input.extend_from_slice(b":1\r\n");
+    let (frame, consumed) = decode::<V2>(&input).unwrap().unwrap();
+    assert_eq!(consumed, input.len());
+    assert_eq!(frame.encode(), input);
+    let mut decoder = Decoder::new();
+    assert_eq!(decoder.decode(&input).unwrap().unwrap().0, frame);
+    let mut arena = DecodeArena::new();
+    assert_eq!(arena.decode(&input).unwrap().unwrap().0.to_frame(), frame);
+    drop(frame);
+
+    for count in [MAX_DEPTH + 1, 100_000] {
+        let mut input = b"*1\r\n".repeat(count);
+        assert_eq!(decode::<V2>(&input), Err(Error::TooDeep));
+        input.extend_from_slice(b":1\r\n");
+        assert_eq!(decode::<V2>(&input), Err(Error::TooDeep));
+        assert_eq!(decoder.decode(&input).map(|_| ()), Err(Error::TooDeep));
+        assert_eq!(arena.decode(&input).map(|_| ()), Err(Error::TooDeep));
+    }
+}
+
+#[test]
+fn decode_v2_binary_bulk() {
+    let input = b"*2\r\n$12\r\nhello\r\nworld\r\n$2\r\n\r\n\r\n";
+    assert_eq!(
+        decode::<V2>(input.as_slice()),
+        Ok(Some((
+            Frame::Array(mini_vec![
+                Frame::BulkString(b"hello\r\nworld"),
+                Frame::BulkString(b"\r\n"),
+            ]),
+            input.len()
+        )))
+    );
+
+    let payload = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+    let input = Frame::BulkString(&payload).encode();
+    assert_eq!(
+        decode::<V2>(&input),
+        Ok(Some((Frame::BulkString(&payload), input.len())))
+    );
+
+    assert_eq!(
+        decode::<V2>(b"$3\r\nhello\r\n".as_slice()),
+        Err(Error::InvalidBulkString)
+    );
+}
+
+#[test]
+fn decode_v2_arena() {
+    let inputs: [&[u8]; 4] = [
+        b"+OK\r\n",
+        b"$-1\r\n",
+        b"*6\r\n:10\r\n:-1\r\n$5\r\nhello\r\n+world\r\n-err\r\n*1\r\n+ok\r\n",
+        b"*2\r\n*2\r\n*1\r\n:1\r\n*0\r\n*1\r\n$3\r\nfoo\r\n",
+    ];
+    let mut arena = DecodeArena::new();
+    for input in inputs {
+        let expected = decode::<V2>(input).unwrap().unwrap();
+        let (frame, consumed) = arena.decode(input).unwrap().unwrap();
+        assert_eq!((frame.to_frame(), consumed), expected);
+    }
+    assert_eq!(arena.decode(b"*2\r\n$3\r\nfoo\r\n"), Ok(None));
+}
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+unsafe impl std::alloc::GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+#[test]
+fn decode_v2_decoder() {
+    let commands: [&[u8]; 3] = [
+        b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n",
+        b"*2\r\n$3\r\nGET\r\n$3\r\nkey\r\n",
+        b"*1\r\n$4\r\nPING\r\n",
+    ];
+    let mut decoder = Decoder::new();
+    for command in commands {
+        let (frame, _) = decoder.decode(command).unwrap().unwrap();
+        decoder.recycle(frame);
+    }
+
+    let before = ALLOCATIONS.with(|count| count.get());
+    for _ in 0..100 {
+        for command in commands {
+            let (frame, consumed) = decoder.decode(command).unwrap().unwrap();
+            assert_eq!(consumed, command.len());
+            decoder.recycle(frame);
+        }
+    }
+    assert_eq!(ALLOCATIONS.with(|count| count.get()), before);
+
+    for command in commands {
+        assert_eq!(decoder.decode(command), decode::<V2>(command));
+    }
+    let before = ALLOCATIONS.with(|count| count.get());
+    decode::<V2>(commands[0]).unwrap();
+    assert!(ALLOCATIONS.with(|count| count.get()) > before);
+}
@@ -1,8 +1,13 @@
+use std::io::{ErrorKind, IoSlice, Result as IoResult, Write};
+
+use bytes::BytesMut;
 use minivec::mini_vec;
+use rand::{rngs::ThreadRng, Rng};
 use rresp::{
     encode,
     v2::{Frame, V2},
-    Error,
+    write_bulk_from_chunks, write_bulk_from_reader, BulkWriter, EncodeLen, EncodeVectored, Error,
+    FrameWriter, VectoredEncoder,
 };
 
 #[test]
@@ -46,3 +51,292 @@ fn test_encode_null() {
     let encoded = encode::<V2>(frame).unwrap();
     assert_eq!(encoded, b"$-1\r\n");
 }
+
+/// Records every call the `FrameWriter` makes, accepting at most `limit`
+/// bytes per call.
+#[derive(Default)]
+struct CallLog {
+    data: Vec<u8>,
+    writes: usize,
+    vectored: Vec<Vec<usize>>,
+    limit: Option<usize>,
+}
+
+impl Write for CallLog {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.writes += 1;
+        let len = self.limit.map_or(buf.len(), |limit| limit.min(buf.len()));
+        self.data.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> IoResult<usize> {
+        self.vectored
+            .push(bufs.iter().map(|buf| buf.len()).collect());
+        let mut written = 0;
+        for buf in bufs {
+            let len = match self.limit {
+                Some(limit) => (limit - written).min(buf.len()),
+                None => buf.len(),
+            };
+            self.data.extend_from_slice(&buf[..len]);
+            written += len;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_frame_writer_pipeline() {
+    let mut writer = FrameWriter::new(CallLog::default());
+    writer.set_auto_flush(None);
+
+    let mut expected = Vec::new();
+    for _ in 0..1000 {
+        let frame = Frame::Array(mini_vec![
+            Frame::BulkString(b"GET"),
+            Frame::BulkString(b"foo")
+        ]);
+        expected.extend_from_slice(&frame.encode());
+        writer.write_frame::<V2>(frame).unwrap();
+    }
+    assert_eq!(writer.get_ref().data.len(), 0);
+    assert_eq!(writer.buffer(), &expected[..]);
+
+    writer.flush().unwrap();
+    let log = writer.into_inner().unwrap();
+    assert_eq!(log.data, expected);
+    assert_eq!(log.writes, 0);
+    assert_eq!(log.vectored, vec![vec![expected.len()]]);
+}
+
+#[test]
+fn test_frame_writer_auto_flush() {
+    let mut writer = FrameWriter::with_capacity(64, CallLog::default());
+    for _ in 0..10 {
+        writer
+            .write_frame::<V2>(Frame::Integer(1234567890))
+            .unwrap();
+    }
+    // Every frame is 13 bytes, so the buffer is flushed after the fifth and
+    // the tenth.
+    assert_eq!(writer.get_ref().vectored, vec![vec![65], vec![65]]);
+    assert!(writer.buffer().is_empty());
+}
+
+#[test]
+fn test_frame_writer_large_payload() {
+    let payload = vec![b'x'; 64 * 1024];
+    let mut writer = FrameWriter::new(CallLog::default());
+    writer
+        .write_frame::<V2>(Frame::BulkString(&payload))
+        .unwrap();
+    writer.flush().unwrap();
+
+    let log = writer.into_inner().unwrap();
+    assert_eq!(log.data, Frame::BulkString(&payload).encode());
+    // The payload goes out together with the buffered header, uncopied.
+    assert_eq!(log.vectored, vec![vec![8, payload.len()], vec![2]]);
+}
+
+#[test]
+fn test_frame_writer_short_writes() {
+    let payload = vec![b'x'; 10 * 1024];
+    let log = CallLog {
+        limit: Some(1000),
+        ..Default::default()
+    };
+    let mut writer = FrameWriter::new(log);
+    let frame = Frame::Array(mini_vec![
+        Frame::SimpleString(b"OK"),
+        Frame::BulkString(&payload),
+        Frame::Integer(1),
+    ]);
+    let expected = frame.encode();
+    writer.write_frame::<V2>(frame).unwrap();
+    let log = writer.into_inner().unwrap();
+    assert_eq!(log.data, expected);
+}
+
+/// Accepts `budget` bytes, then fails once.
+struct FailOnce {
+    data: Vec<u8>,
+    budget: Option<usize>,
+}
+
+impl Write for FailOnce {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.write_vectored(&[IoSlice::new(buf)])
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> IoResult<usize> {
+        let budget = match self.budget {
+            Some(0) => {
+                self.budget = None;
+                return Err(ErrorKind::BrokenPipe.into());
+            }
+            Some(budget) => budget,
+            None => usize::MAX,
+        };
+        let mut written = 0;
+        for buf in bufs {
+            let len = (budget - written).min(buf.len());
+            self.data.extend_from_slice(&buf[..len]);
+            written += len;
+        }
+        self.budget = self.budget.map(|budget| budget - written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_frame_writer_failed_writes() {
+    let payload = vec![b'x'; 8 * 1024];
+    let mut expected = b"+OK\r\n+OK\r\n".to_vec();
+    expected.extend_from_slice(&payload);
+
+    // The error comes once part of the payload is out: that part is
+    // reported and the rest follows, without sending anything twice.
+    // Failing within the buffer keeps the payload buffered, and failing
+    // before anything is out reports the error.
+    for budget in [100, 4, 0] {
+        let mut writer = FrameWriter::new(FailOnce {
+            data: Vec::new(),
+            budget: Some(budget),
+        });
+        writer.write_all(b"+OK\r\n+OK\r\n").unwrap();
+        match writer.write_all(&payload) {
+            Ok(()) => {}
+            Err(e) if budget == 0 => {
+                assert_eq!(e.kind(), ErrorKind::BrokenPipe);
+                writer.write_all(&payload).unwrap();
+            }
+            Err(e) => panic!("{}", e),
+        }
+        writer.flush().unwrap();
+        assert_eq!(writer.get_ref().data, expected, "budget {}", budget);
+    }
+}
+
+#[test]
+fn test_encode_vectored() {
+    let payload = vec![b'z'; 2048];
+    let frame = Frame::Array(mini_vec![
+        Frame::BulkString(b"small"),
+        Frame::BulkString(&payload),
+        Frame::Integer(-1),
+        Frame::Null,
+    ]);
+    let expected = frame.encode();
+
+    let mut encoder = VectoredEncoder::new();
+    V2::encode_vectored(frame, &mut encoder);
+    let segments = encoder.segments().collect::<Vec<_>>();
+    assert_eq!(segments.len(), 3);
+    assert_eq!(segments[1].as_ptr(), payload.as_ptr());
+    assert_eq!(segments.concat(), expected);
+}
+
+#[test]
+fn test_encode_to_buf() {
+    let frame = Frame::Array(mini_vec![
+        Frame::Array(mini_vec![
+            Frame::BulkString(b"hello"),
+            Frame::Integer(i64::MIN)
+        ]),
+        Frame::Null,
+        Frame::SimpleError(b"ERR"),
+        Frame::SimpleString(b"OK"),
+        Frame::BulkString(b""),
+    ]);
+
+    let mut buf = BytesMut::new();
+    frame.encode_to_buf(&mut buf);
+    assert_eq!(
+        &buf[..],
+        b"*5\r\n*2\r\n$5\r\nhello\r\n:-9223372036854775808\r\n$-1\r\n-ERR\r\n+OK\r\n$0\r\n\r\n"
+    );
+
+    let mut writer = Vec::new();
+    frame.encode_with_writer(&mut writer).unwrap();
+    assert_eq!(&buf[..], writer);
+    assert_eq!(frame.encode(), writer);
+}
+
+fn random_frame<'a>(rng: &mut ThreadRng, pool: &'a [u8], depth: usize) -> Frame<'a> {
+    let text = &pool[..rng.random_range(0..pool.len())];
+    match rng.random_range(0..if depth == 0 { 5 } else { 6 }) {
+        0 => Frame::Null,
+        1 => Frame::Integer(match rng.random_range(0..3) {
+            0 => rng.random(),
+            1 => rng.random_range(-1000..1000),
+            _ => *[0, i64::MIN, i64::MAX, -1, 10, -10]
+                .get(rng.random_range(0..6))
+                .unwrap(),
+        }),
+        2 => Frame::SimpleString(&text[..text.len().min(64)]),
+        3 => Frame::SimpleError(&text[..text.len().min(64)]),
+        4 => Frame::BulkString(text),
+        _ => Frame::Array(
+            (0..rng.random_range(0..12))
+                .map(|_| random_frame(rng, pool, depth - 1))
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn test_encode_len_matches_encode() {
+    let pool = vec![b'a'; 20_000];
+    let mut rng = rand::rng();
+    for _ in 0..2000 {
+        let frame = random_frame(&mut rng, &pool, 3);
+        assert_eq!(frame.encode_len(), frame.encode().len(), "{:?}", frame);
+    }
+}
+
+#[test]
+fn test_write_bulk_streaming() {
+    let payload = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+    let expected = Frame::BulkString(&payload).encode();
+
+    let mut out = Vec::new();
+    write_bulk_from_reader(&mut out, payload.len(), &payload[..]).unwrap();
+    assert_eq!(out, expected);
+
+    let mut out = Vec::new();
+    write_bulk_from_chunks(&mut out, payload.len(), payload.chunks(777)).unwrap();
+    assert_eq!(out, expected);
+
+    let mut out = Vec::new();
+    let mut bulk = BulkWriter::new(&mut out, 5).unwrap();
+    bulk.write_all(b"he").unwrap();
+    assert_eq!(bulk.remaining(), 3);
+    bulk.copy_from(&b"llo world"[..]).unwrap();
+    bulk.finish().unwrap();
+    assert_eq!(out, b"$5\r\nhello\r\n");
+}
+
+#[test]
+fn test_write_bulk_length_mismatch() {
+    let mut out = Vec::new();
+    let err = write_bulk_from_reader(&mut out, 10, &b"short"[..]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+    let mut out = Vec::new();
+    let err = write_bulk_from_chunks(&mut out, 4, [&b"ab"[..], b"cde"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mut out = Vec::new();
+    let mut bulk = BulkWriter::new(&mut out, 4).unwrap();
+    bulk.write_all(b"abc").unwrap();
+    assert_eq!(bulk.finish().unwrap_err().kind(), ErrorKind::InvalidInput);
+}
@@ -1,9 +1,12 @@
 use minivec::mini_vec;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use rresp::{
     decode,
-    v3::{Frame, V3},
+    v3::{BigNumberBuf, DecodeArena, Frame, FrameRef, VerbatimFormat, V3},
+    visit::{visit, MapAccess, SeqAccess, Visitor},
+    BulkDecoder, BulkEvent, BulkHeader, Error, FrameReader, Invalidation, Message, ParseIter,
+    Redirect, RedisErrorKind, MAX_DEPTH,
 };
 
 #[test]
@@ -131,7 +134,16 @@ fn decode_v3() {
 
     let input = b"_\r\n";
     let (frame, remaining) = decode::<V3>(input.as_slice()).unwrap().unwrap();
-    assert_eq!((frame, remaining), (Frame::Null { data: () }, 3));
+    assert_eq!(
+        (frame, remaining),
+        (
+            Frame::Null {
+                data: (),
+                attributes: None
+            },
+            3
+        )
+    );
 
     let input = b"#t\r\n";
     let (frame, remaining) = decode::<V3>(input.as_slice()).unwrap().unwrap();
@@ -166,12 +178,45 @@ fn decode_v3() {
         (
             Frame::Double {
                 data: 123.45,
+                raw: None,
                 attributes: None
             },
             9
         )
     );
 
+    for (input, expected) in [
+        (b",-inf\r\n".as_slice(), Ok(f64::NEG_INFINITY)),
+        (b",1.23e-10\r\n", Ok(1.23e-10)),
+        (b",NaN\r\n", Err(Error::InvalidDouble)),
+        (b",.5\r\n", Err(Error::InvalidDouble)),
+    ] {
+        let decoded = decode::<V3>(input).map(|frame| match frame {
+            Some((Frame::Double { data, .. }, _)) => data,
+            other => panic!("unexpected {:?}", other),
+        });
+        assert_eq!(decoded, expected);
+    }
+
+    // Doubles compare like they hash, so `nan` is a usable key and the two
+    // zeros are different ones.
+    let (nan, _) = decode::<V3>(b",nan\r\n").unwrap().unwrap();
+    assert_eq!(nan, nan.clone());
+    let (zero, _) = decode::<V3>(b",0\r\n").unwrap().unwrap();
+    let (negative_zero, _) = decode::<V3>(b",-0\r\n").unwrap().unwrap();
+    assert_ne!(zero, negative_zero);
+    let set = HashSet::from([nan.clone(), zero, negative_zero]);
+    assert_eq!(set.len(), 3);
+    assert!(set.contains(&nan));
+    let (frame, _) = decode::<V3>(b"~2\r\n,nan\r\n,nan\r\n").unwrap().unwrap();
+    assert_eq!(
+        frame,
+        Frame::Set {
+            data: HashSet::from([nan]),
+            attributes: None,
+        }
+    );
+
     let input = b"|1\r\n+key\r\n+value\r\n+main\r\n";
     let (frame, remaining) = decode::<V3>(input.as_slice()).unwrap().unwrap();
     let attributes = HashMap::from([(
@@ -195,3 +240,793 @@ fn decode_v3() {
         )
     );
 }
+
+#[test]
+fn decode_v3_invalidation() {
+    let input = b">2\r\n$10\r\ninvalidate\r\n*2\r\n$3\r\nfoo\r\n$3\r\nbar\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(
+        frame.as_invalidation(),
+        Some(Invalidation::Keys(vec![
+            b"foo".as_slice(),
+            b"bar".as_slice()
+        ]))
+    );
+
+    let input = b">2\r\n$10\r\ninvalidate\r\n_\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(frame.as_invalidation(), Some(Invalidation::Flush));
+
+    let input = b"*2\r\n$10\r\ninvalidate\r\n_\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(frame.as_invalidation(), None);
+}
+
+#[test]
+fn decode_v3_messages() {
+    let input = b"+OK\r\n>2\r\n$10\r\ninvalidate\r\n*1\r\n$3\r\nfoo\r\n:1\r\n";
+    let messages = V3::parse_iter(input.as_slice())
+        .messages()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(messages.len(), 3);
+    assert!(!messages[0].is_push());
+    assert!(!messages[2].is_push());
+    match &messages[1] {
+        Message::Push(frame) => assert_eq!(
+            frame.as_invalidation(),
+            Some(Invalidation::Keys(vec![b"foo".as_slice()]))
+        ),
+        Message::Reply(_) => panic!("expected a push frame"),
+    }
+}
+
+#[test]
+fn decode_v3_error() {
+    let input = b"-MOVED 3999 127.0.0.1:6381\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    let error = frame.as_error().unwrap();
+    assert_eq!(
+        error.kind(),
+        RedisErrorKind::Moved(Redirect {
+            slot: 3999,
+            host: b"127.0.0.1",
+            port: 6381,
+        })
+    );
+
+    let input = b"!21\r\nSYNTAX invalid syntax\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    let error = frame.as_error().unwrap();
+    assert_eq!(error.code(), b"SYNTAX");
+    assert_eq!(error.message(), b"invalid syntax");
+
+    let input = b"+OK\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(frame.as_error(), None);
+}
+
+#[test]
+fn decode_v3_frame_reader() {
+    let input = b"#t\r\n%1\r\n$3\r\nbar\r\n:1\r\n";
+    let mut reader = FrameReader::<_, V3>::with_capacity(2, input.as_slice());
+
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((
+            Frame::Boolean {
+                data: true,
+                attributes: None
+            },
+            4
+        ))
+    );
+    let (frame, _) = reader.read_frame().unwrap().unwrap();
+    assert_eq!(
+        frame,
+        Frame::Map {
+            data: HashMap::from([(
+                Frame::BulkString {
+                    data: b"bar",
+                    attributes: None
+                },
+                Frame::Integer {
+                    data: 1,
+                    attributes: None
+                }
+            )]),
+            attributes: None,
+        }
+    );
+    drop(frame);
+    assert_eq!(reader.read_frame().unwrap(), None);
+}
+
+/// Feeds `input` to a fresh decoder in pieces of `step` bytes, collecting the
+/// events with their chunks joined.
+fn bulk_events(input: &[u8], step: usize) -> (Option<BulkHeader>, Vec<u8>) {
+    let mut decoder = BulkDecoder::new();
+    let (mut header, mut payload) = (None, Vec::new());
+    let (mut start, mut end) = (0, 0);
+    while !decoder.is_done() {
+        match decoder.decode(&input[start..end]).unwrap() {
+            Some((BulkEvent::Header(h), used)) => {
+                header = Some(h);
+                start += used;
+            }
+            Some((BulkEvent::Chunk(chunk), used)) => {
+                payload.extend_from_slice(chunk);
+                start += used;
+            }
+            Some((BulkEvent::End, used)) => start += used,
+            None => {
+                assert!(end < input.len(), "decoder wants more than the input");
+                end = (end + step).min(input.len());
+            }
+        }
+    }
+    assert_eq!(start, input.len());
+    (header, payload)
+}
+
+#[test]
+fn decode_v3_bulk_decoder() {
+    let streamed = b"$?\r\n;4\r\nHell\r\n;7\r\no world\r\n;0\r\n";
+    for step in [1, 2, 3, 5, streamed.len()] {
+        assert_eq!(
+            bulk_events(streamed, step),
+            (Some(BulkHeader::Streamed), b"Hello world".to_vec())
+        );
+        assert_eq!(
+            bulk_events(b"$12\r\nhello\r\nworld\r\n", step),
+            (Some(BulkHeader::Len(12)), b"hello\r\nworld".to_vec())
+        );
+        assert_eq!(
+            bulk_events(b"-ERR no such key\r\n", step),
+            (Some(BulkHeader::Error(15)), b"ERR no such key".to_vec())
+        );
+        assert_eq!(
+            bulk_events(b"!11\r\nERR\r\nfailed\r\n", step),
+            (Some(BulkHeader::Error(11)), b"ERR\r\nfailed".to_vec())
+        );
+    }
+    assert_eq!(
+        bulk_events(b"_\r\n", 1),
+        (Some(BulkHeader::Null), Vec::new())
+    );
+    assert_eq!(
+        bulk_events(b"$-1\r\n", 1),
+        (Some(BulkHeader::Null), Vec::new())
+    );
+
+    let mut decoder = BulkDecoder::new();
+    assert_eq!(
+        decoder.decode(b"$5\r\nhello!!"),
+        Ok(Some((BulkEvent::Header(BulkHeader::Len(5)), 4)))
+    );
+    assert_eq!(
+        decoder.decode(b"hello!!"),
+        Ok(Some((BulkEvent::Chunk(b"hello"), 5)))
+    );
+    assert_eq!(decoder.decode(b"!!"), Err(Error::InvalidBulkString));
+
+    assert_eq!(
+        BulkDecoder::new().decode(b"+OK\r\n"),
+        Err(Error::InvalidBulkString)
+    );
+}
+
+#[test]
+fn decode_v3_read_bulk() {
+    let input = b"$?\r\n;3\r\nabc\r\n;2\r\nde\r\n;0\r\n_\r\n#t\r\n";
+    let mut reader = FrameReader::<_, V3>::with_capacity(4, input.as_slice());
+    let mut sink = Vec::new();
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), Some(5));
+    assert_eq!(sink, b"abcde");
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), None);
+    assert_eq!(
+        reader.read_frame().unwrap(),
+        Some((
+            Frame::Boolean {
+                data: true,
+                attributes: None,
+            },
+            4
+        ))
+    );
+
+    // Error replies are consumed and surface the server's message.
+    let input = b"-WRONGTYPE Operation against a key\r\n!11\r\nERR\r\nfailed\r\n$2\r\nok\r\n";
+    let mut reader = FrameReader::<_, V3>::with_capacity(4, input.as_slice());
+    let mut sink = Vec::new();
+    let error = reader.read_bulk(&mut sink).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::Other);
+    assert_eq!(error.to_string(), "WRONGTYPE Operation against a key");
+    let error = reader.read_bulk(&mut sink).unwrap_err();
+    assert_eq!(error.to_string(), "ERR\r\nfailed");
+    assert_eq!(reader.read_bulk(&mut sink).unwrap(), Some(2));
+    assert_eq!(sink, b"ok");
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Option<&'de [u8]>;
+
+    fn visit_bulk(self, data: &'de [u8]) -> Result<Self::Value, Error> {
+        Ok(Some(data))
+    }
+
+    fn visit_null(self) -> Result<Self::Value, Error> {
+        Ok(None)
+    }
+}
+
+struct IntegerVisitor;
+
+impl<'de> Visitor<'de> for IntegerVisitor {
+    type Value = i64;
+
+    fn visit_integer(self, data: i64) -> Result<Self::Value, Error> {
+        Ok(data)
+    }
+}
+
+/// Collects `MGET`-style replies.
+struct VecVisitor;
+
+impl<'de> Visitor<'de> for VecVisitor {
+    type Value = Vec<Option<&'de [u8]>>;
+
+    fn visit_array<A>(self, len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::with_capacity(len);
+        while let Some(value) = seq.next_element(BytesVisitor)? {
+            values.push(value);
+        }
+        Ok(values)
+    }
+}
+
+/// Collects `HGETALL`-style replies.
+struct HashMapVisitor;
+
+impl<'de> Visitor<'de> for HashMapVisitor {
+    type Value = HashMap<&'de [u8], i64>;
+
+    fn visit_map<A>(self, len: usize, map: &mut A) -> Result<Self::Value, Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut values = HashMap::with_capacity(len);
+        while let Some(key) = map.next_key(BytesVisitor)? {
+            values.insert(
+                key.ok_or(Error::InvalidMap)?,
+                map.next_value(IntegerVisitor)?,
+            );
+        }
+        Ok(values)
+    }
+}
+
+/// Reads the first element only, leaving the rest to be skipped.
+struct FirstVisitor;
+
+impl<'de> Visitor<'de> for FirstVisitor {
+    type Value = i64;
+
+    fn visit_array<A>(self, _len: usize, seq: &mut A) -> Result<Self::Value, Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        seq.next_element(IntegerVisitor)?.ok_or(Error::InvalidArray)
+    }
+}
+
+#[test]
+fn decode_v3_visit() {
+    let input = b"*3\r\n$3\r\nfoo\r\n_\r\n+bar\r\n:1\r\n";
+    assert_eq!(
+        visit::<V3, _>(input, VecVisitor),
+        Ok(Some((vec![Some(&b"foo"[..]), None, Some(b"bar")], 22)))
+    );
+
+    let input = b"%2\r\n$1\r\na\r\n:1\r\n|1\r\n+ttl\r\n:3\r\n$1\r\nb\r\n:2\r\n";
+    assert_eq!(
+        visit::<V3, _>(input, HashMapVisitor),
+        Ok(Some((
+            HashMap::from([(&b"a"[..], 1), (b"b", 2)]),
+            input.len()
+        )))
+    );
+
+    let input = b"*3\r\n:7\r\n%1\r\n+k\r\n*1\r\n:1\r\n~1\r\n:2\r\n:9\r\n";
+    let (value, consumed) = visit::<V3, _>(input, FirstVisitor).unwrap().unwrap();
+    assert_eq!(value, 7);
+    assert_eq!(
+        visit::<V3, _>(&input[consumed..], IntegerVisitor),
+        Ok(Some((9, 4)))
+    );
+
+    assert_eq!(
+        visit::<V3, _>(b"#t\r\n", IntegerVisitor),
+        Err(Error::InvalidType)
+    );
+    assert_eq!(
+        visit::<V3, _>(b"*2\r\n$1\r\na\r\n", VecVisitor),
+        Err(Error::NotComplete)
+    );
+    assert_eq!(visit::<V3, _>(b"", VecVisitor), Ok(None));
+}
+
+#[test]
+fn decode_v3_spanned() {
+    let input = b">2\r\n$7\r\nmessage\r\n$2\r\nhi\r\n|1\r\n+ttl\r\n:3\r\n#t\r\n%1\r\n+k\r\n:1\r\n";
+    let frames = V3::parse_iter(input.as_slice())
+        .spanned()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let spans = frames
+        .iter()
+        .map(|(_, span)| span.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(spans, vec![0..25, 25..43, 43..55]);
+    assert_eq!(&input[25..43], b"|1\r\n+ttl\r\n:3\r\n#t\r\n");
+    assert!(frames[0].0.is_push());
+    for (frame, span) in frames {
+        assert_eq!(
+            decode::<V3>(&input[span.clone()]),
+            Ok(Some((frame, span.len())))
+        );
+    }
+
+    let mut spanned = V3::parse_iter(b"-ERR\r\n?\r\n".as_slice()).spanned();
+    assert!(matches!(
+        spanned.next(),
+        Some(Ok((Frame::SimpleError { .. }, _)))
+    ));
+    assert!(matches!(spanned.next(), Some(Err(_))));
+}
+
+/// What [`decode`] makes of `input`, in the terms `frame_len` reports.
+fn decoded_len(input: &[u8]) -> Result<Option<usize>, Error> {
+    match decode::<V3>(input) {
+        Ok(frame) => Ok(frame.map(|(_, len)| len)),
+        Err(Error::NotComplete) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[test]
+fn decode_v3_frame_len() {
+    let inputs: [&[u8]; 12] = [
+        b"_\r\n",
+        b"!21\r\nSYNTAX invalid syntax\r\n",
+        b"%2\r\n+first\r\n:1\r\n+second\r\n:2\r\n",
+        b"~2\r\n#t\r\n,1.5\r\n",
+        b">2\r\n$7\r\nmessage\r\n$2\r\nhi\r\n",
+        b"|1\r\n+ttl\r\n:3\r\n#t\r\n",
+        b"*2\r\n(12\r\n:2\r\n",
+        b"*-1\r\n",
+        b"|0\r\n%1\r\n|1\r\n+a\r\n*0\r\n*1\r\n:1\r\n~0\r\n",
+        b"~1\r\n*2\r\n|1\r\n+a\r\n:1\r\n:2\r\n*0\r\n",
+        b"*2\r\n|1\r\n+ttl\r\n:3\r\n$3\r\nfoo\r\n|1\r\n+a\r\n*1\r\n|1\r\n+b\r\n:1\r\n:1\r\n%1\r\n+k\r\n:2\r\n",
+        b">1\r\n%1\r\n+k\r\n>1\r\n~1\r\n:1\r\n",
+    ];
+    for input in inputs {
+        let (_, len) = decode::<V3>(input).unwrap().unwrap();
+        assert_eq!(V3::frame_len(input), Ok(Some(len)));
+        for end in 0..input.len() {
+            assert_eq!(V3::frame_len(&input[..end]), Ok(None));
+        }
+    }
+    assert_eq!(
+        V3::frame_len(b"%1\r\n+a\r\n#?\r\n"),
+        Err(Error::InvalidBoolean)
+    );
+
+    // Both accept and refuse the same frames, for the same reasons.
+    let mut deep = b"*1\r\n".repeat(MAX_DEPTH);
+    deep.extend_from_slice(b"|1\r\n+a\r\n:1\r\n");
+    let invalid: [&[u8]; 12] = [
+        b"_x\r\n",
+        b"~1\r\n%0\r\n",
+        b"~1\r\n>0\r\n",
+        b"%1\r\n%0\r\n:1\r\n",
+        b"%1\r\n~0\r\n:1\r\n",
+        b"|1\r\n+a\r\n%1\r\n+b\r\n+c\r\n:1\r\n",
+        b"|1\r\n~0\r\n:1\r\n:1\r\n",
+        b"|1\r\n+a\r\n>0\r\n:1\r\n",
+        b"|1\r\n+a\r\n:1\r\n|1\r\n+b\r\n:2\r\n:3\r\n",
+        b"*1\r\n|0\r\n|0\r\n:3\r\n",
+        b"*2\r\n:1\r\n,1.5.\r\n",
+        &deep,
+    ];
+    for input in inputs.iter().chain(&invalid) {
+        for end in 0..=input.len() {
+            assert_eq!(
+                V3::frame_len(&input[..end]),
+                decoded_len(&input[..end]),
+                "{:?}",
+                String::from_utf8_lossy(&input[..end])
+            );
+        }
+    }
+    for input in invalid {
+        assert!(V3::frame_len(input).is_err());
+    }
+}
+
+#[test]
+fn decode_v3_binary_bulk() {
+    let input = b"*3\r\n$12\r\nhello\r\nworld\r\n!4\r\nE\r\nR\r\n=8\r\ntxt:a\r\nb\r\n";
+    let (frame, remaining) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(remaining, input.len());
+    let Frame::Array { data, .. } = frame else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        data[0],
+        Frame::BulkString {
+            data: b"hello\r\nworld",
+            attributes: None,
+        }
+    );
+    assert_eq!(
+        data[1],
+        Frame::BulkError {
+            data: b"E\r\nR",
+            attributes: None,
+        }
+    );
+
+    let payload = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+    let input = Frame::BulkString {
+        data: &payload,
+        attributes: None,
+    }
+    .encode();
+    assert_eq!(
+        decode::<V3>(&input),
+        Ok(Some((
+            Frame::BulkString {
+                data: &payload,
+                attributes: None,
+            },
+            input.len()
+        )))
+    );
+
+    assert_eq!(
+        decode::<V3>(b"$3\r\nhello\r\n".as_slice()),
+        Err(Error::InvalidBulkString)
+    );
+}
+
+#[test]
+fn decode_v3_arena() {
+    let inputs: [&[u8]; 5] = [
+        b"_\r\n",
+        b"*3\r\n:1\r\n*2\r\n$3\r\nfoo\r\n#f\r\n(12345678901234567890\r\n",
+        b"%2\r\n+first\r\n:1\r\n+second\r\n*1\r\n,2.5\r\n",
+        b"~2\r\n#t\r\n!3\r\nERR\r\n",
+        b">2\r\n$7\r\nmessage\r\n$2\r\nhi\r\n",
+    ];
+    let mut arena = DecodeArena::new();
+    for input in inputs {
+        let expected = decode::<V3>(input).unwrap().unwrap();
+        let (frame, consumed) = arena.decode(input).unwrap().unwrap();
+        assert_eq!((frame.to_frame(), consumed), expected);
+    }
+
+    let input = b"*2\r\n:1\r\n:2\r\n";
+    let (frame, _) = arena.decode(input).unwrap().unwrap();
+    let FrameRef::Array { data, .. } = frame else {
+        panic!("expected an array");
+    };
+    let numbers = data
+        .iter()
+        .map(|frame| match frame {
+            FrameRef::Integer { data, .. } => data,
+            _ => panic!("expected an integer"),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(numbers, vec![1, 2]);
+}
+
+#[test]
+fn decode_v3_deep_nesting() {
+    // Nests `depth` aggregates around `:1`, the outermost from `head` and
+    // the rest from `level`. A set only ever heads the nesting, since
+    // nothing inside a set may hold one.
+    fn nest(head: &[u8], level: &[u8], depth: usize) -> Vec<u8> {
+        let mut input = head.to_vec();
+        input.extend(level.repeat(depth - 1));
+        input.extend_from_slice(b":1\r\n");
+        input
+    }
+
+    let levels: [(&[u8], &[u8]); 5] = [
+        (b"*1\r\n", b"*1\r\n"),
+        (b"%1\r\n+key\r\n", b"%1\r\n+key\r\n"),
+        (b"~1\r\n", b"*1\r\n"),
+        (b">1\r\n", b">1\r\n"),
+        (b"|1\r\n+key\r\n:1\r\n*1\r\n", b"|1\r\n+key\r\n:1\r\n*1\r\n"),
+    ];
+    for (head, level) in levels {
+        let input = nest(head, level, MAX_DEPTH);
+        let (frame, consumed) = decode::<V3>(&input).unwrap().unwrap();
+        assert_eq!(consumed, input.len());
+        assert_eq!(frame, frame.clone());
+        assert_eq!(frame.encode(), input);
+        let mut arena = DecodeArena::new();
+        assert_eq!(arena.decode(&input).unwrap().unwrap().0.to_frame(), frame);
+        drop(frame);
+
+        // One level more, or a hostile reply far deeper, complete or not,
+        // fails before any frame is built.
+        for depth in [MAX_DEPTH + 1, 100_000] {
+            let input = nest(head, level, depth);
+            let incomplete = &input[..input.len() - 4];
+            assert_eq!(decode::<V3>(incomplete), Err(Error::TooDeep));
+            assert_eq!(decode::<V3>(&input), Err(Error::TooDeep));
+            assert_eq!(arena.decode(&input).map(|_| ()), Err(Error::TooDeep));
+        }
+    }
+
+    let input = b"%1\r\n".repeat(100_000);
+    assert_eq!(decode::<V3>(&input), Err(Error::InvalidMap));
+}
+
+#[test]
+fn decode_v3_huge_lengths() {
+    // Headers claiming more elements than the input holds reserve nothing
+    // up front and just wait for more input.
+    for tag in ["*", "%", "~", ">", "|"] {
+        let input = format!("{}1152921504606846975\r\n:5\r\n", tag);
+        assert_eq!(
+            decode::<V3>(input.as_bytes()),
+            Err(Error::NotComplete),
+            "{}",
+            tag
+        );
+    }
+}
+
+#[test]
+fn decode_v3_nested_attributes() {
+    let input = b"*2\r\n|1\r\n+ttl\r\n:3\r\n$3\r\nfoo\r\n|1\r\n+a\r\n*1\r\n|1\r\n+b\r\n:1\r\n:1\r\n%1\r\n+k\r\n:2\r\n";
+    let (frame, consumed) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(consumed, input.len());
+    assert_eq!(
+        frame,
+        Frame::Array {
+            data: mini_vec![
+                Frame::BulkString {
+                    data: b"foo",
+                    attributes: Some(HashMap::from([(
+                        Frame::SimpleString {
+                            data: b"ttl",
+                            attributes: None
+                        },
+                        Frame::Integer {
+                            data: 3,
+                            attributes: None
+                        }
+                    )]))
+                },
+                Frame::Map {
+                    data: HashMap::from([(
+                        Frame::SimpleString {
+                            data: b"k",
+                            attributes: None
+                        },
+                        Frame::Integer {
+                            data: 2,
+                            attributes: None
+                        }
+                    )]),
+                    attributes: Some(HashMap::from([(
+                        Frame::SimpleString {
+                            data: b"a",
+                            attributes: None
+                        },
+                        Frame::Array {
+                            data: mini_vec![Frame::Integer {
+                                data: 1,
+                                attributes: Some(HashMap::from([(
+                                    Frame::SimpleString {
+                                        data: b"b",
+                                        attributes: None
+                                    },
+                                    Frame::Integer {
+                                        data: 1,
+                                        attributes: None
+                                    }
+                                )]))
+                            }],
+                            attributes: None
+                        }
+                    )]))
+                },
+            ],
+            attributes: None,
+        }
+    );
+
+    let mut arena = DecodeArena::new();
+    let (frame, _) = arena.decode(input).unwrap().unwrap();
+    assert_eq!(
+        frame.to_frame(),
+        decode::<V3>(input.as_slice()).unwrap().unwrap().0
+    );
+}
+
+#[test]
+fn decode_v3_null_and_push_attributes() {
+    let input = b"|1\r\n+ttl\r\n:3\r\n_\r\n|1\r\n+key\r\n+val\r\n>2\r\n+message\r\n+hi\r\n";
+    let frames = V3::parse_iter(input.as_slice())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let ttl = HashMap::from([(
+        Frame::SimpleString {
+            data: b"ttl",
+            attributes: None,
+        },
+        Frame::Integer {
+            data: 3,
+            attributes: None,
+        },
+    )]);
+    let key = HashMap::from([(
+        Frame::SimpleString {
+            data: b"key",
+            attributes: None,
+        },
+        Frame::SimpleString {
+            data: b"val",
+            attributes: None,
+        },
+    )]);
+    assert_eq!(
+        frames,
+        vec![
+            Frame::Null {
+                data: (),
+                attributes: Some(ttl.clone()),
+            },
+            Frame::Push {
+                data: mini_vec![
+                    Frame::SimpleString {
+                        data: b"message",
+                        attributes: None,
+                    },
+                    Frame::SimpleString {
+                        data: b"hi",
+                        attributes: None,
+                    },
+                ],
+                attributes: Some(key.clone()),
+            },
+        ]
+    );
+    assert_eq!(frames[0].attributes(), Some(&ttl));
+    assert_eq!(frames[1].attributes(), Some(&key));
+
+    let encoded = frames.iter().flat_map(Frame::encode).collect::<Vec<_>>();
+    assert_eq!(encoded, input);
+
+    let mut arena = DecodeArena::new();
+    let (frame, consumed) = arena.decode(input).unwrap().unwrap();
+    assert!(matches!(
+        frame,
+        FrameRef::Null {
+            attributes: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(frame.to_frame(), frames[0]);
+    let (frame, _) = arena.decode(&input[consumed..]).unwrap().unwrap();
+    assert!(matches!(
+        frame,
+        FrameRef::Push {
+            attributes: Some(_),
+            ..
+        }
+    ));
+    assert_eq!(frame.to_frame(), frames[1]);
+}
+
+#[test]
+fn decode_v3_big_number() {
+    let input = b"(-3492890328409238509324850943850943825024385\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(frame.as_i128(), None);
+    assert_eq!(frame.as_u128(), None);
+    #[cfg(feature = "num-bigint")]
+    {
+        let number = frame.as_big_int().unwrap();
+        assert_eq!(number.to_string().as_bytes(), &input[1..input.len() - 2]);
+        assert_eq!(BigNumberBuf::from(number).as_frame(), frame);
+    }
+
+    // The spec allows a leading `+`.
+    let (frame, _) = decode::<V3>(b"(+12\r\n").unwrap().unwrap();
+    assert_eq!(frame.as_i128(), Some(12));
+    assert_eq!(frame.as_u128(), Some(12));
+    #[cfg(feature = "num-bigint")]
+    assert_eq!(frame.as_big_int(), Some(12.into()));
+
+    let (frame, _) = decode::<V3>(b"(170141183460469231731687303715884105727\r\n")
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.as_i128(), Some(i128::MAX));
+    assert_eq!(frame.as_u128(), Some(i128::MAX as u128));
+    let number = BigNumberBuf::from(i128::MAX);
+    assert_eq!(number.as_frame(), frame);
+    assert_eq!(
+        number.as_frame().encode(),
+        b"(170141183460469231731687303715884105727\r\n"
+    );
+
+    for input in [
+        b"(abc\r\n".as_slice(),
+        b"(\r\n",
+        b"(-\r\n",
+        b"(1.5\r\n",
+        b"(+\r\n",
+    ] {
+        assert_eq!(decode::<V3>(input), Err(Error::InvalidBigNumber));
+        assert_eq!(
+            DecodeArena::new().decode(input).map(|_| ()),
+            Err(Error::InvalidBigNumber)
+        );
+    }
+}
+
+#[test]
+fn decode_v3_verbatim_string() {
+    let input = b"=15\r\ntxt:Some string\r\n";
+    let (frame, _) = decode::<V3>(input.as_slice()).unwrap().unwrap();
+    assert_eq!(
+        frame,
+        Frame::VerbatimString {
+            data: (*b"txt", b"Some string"),
+            attributes: None,
+        }
+    );
+    assert_eq!(frame.verbatim_format(), Some(VerbatimFormat::Text));
+    assert_eq!(frame.as_verbatim_str(), Some("Some string"));
+    assert_eq!(frame.encode(), input);
+    let mut writer = Vec::new();
+    frame.encode_with_writer(&mut writer).unwrap();
+    assert_eq!(writer, input);
+    assert_eq!(V3::frame_len(input), Ok(Some(input.len())));
+
+    let mut arena = DecodeArena::new();
+    let (frame_ref, _) = arena.decode(input).unwrap().unwrap();
+    assert_eq!(frame_ref.to_frame(), frame);
+
+    let frame = Frame::VerbatimString {
+        data: (VerbatimFormat::Markdown.into(), b"\xff"),
+        attributes: None,
+    };
+    let encoded = frame.encode();
+    assert_eq!(encoded, b"=5\r\nmkd:\xff\r\n");
+    let (frame, _) = decode::<V3>(&encoded).unwrap().unwrap();
+    assert_eq!(frame.verbatim_format(), Some(VerbatimFormat::Markdown));
+    assert_eq!(frame.as_verbatim_str(), None);
+
+    for input in [b"=3\r\ntxt\r\n".as_slice(), b"=8\r\ntxt-abcd\r\n"] {
+        assert_eq!(decode::<V3>(input), Err(Error::InvalidVerbatimString));
+        assert_eq!(V3::frame_len(input), Err(Error::InvalidVerbatimString));
+        assert_eq!(
+            arena.decode(input).map(|_| ()),
+            Err(Error::InvalidVerbatimString)
+        );
+    }
+}
@@ -1,9 +1,16 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Result as IoResult, Write},
+};
 
+use bytes::BytesMut;
+use minivec::mini_vec;
+use rand::{rngs::ThreadRng, Rng};
 use rresp::{
-    encode,
+    decode, encode,
     v3::{Frame, V3},
-    EncodeLen,
+    write_streamed_string, EncodeLen, EncodeToBuf, EncodeVectored, FrameWriter, ParseIter,
+    StreamedStringWriter, VectoredEncoder,
 };
 
 #[test]
@@ -49,3 +56,450 @@ fn v3_encode() {
     let encodeed = encode::<V3>(frame).unwrap();
     assert_eq!(encodeed, b"$5\r\nhello\r\n");
 }
+
+/// Accepts a single byte per call, like a congested socket.
+struct ByteWriter(Vec<u8>);
+
+impl Write for ByteWriter {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.extend_from_slice(&buf[..buf.len().min(1)]);
+        Ok(buf.len().min(1))
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn v3_frame_writer() {
+    let frame = Frame::Map {
+        data: HashMap::from([(
+            Frame::BulkString {
+                data: b"key",
+                attributes: None,
+            },
+            Frame::Double {
+                data: 1.5,
+                raw: None,
+                attributes: None,
+            },
+        )]),
+        attributes: Some(HashMap::from([(
+            Frame::SimpleString {
+                data: b"ttl",
+                attributes: None,
+            },
+            Frame::Integer {
+                data: 100,
+                attributes: None,
+            },
+        )])),
+    };
+    let expected = frame.encode();
+
+    let mut writer = FrameWriter::new(ByteWriter(Vec::new()));
+    writer.write_frame::<V3>(frame.clone()).unwrap();
+    assert!(writer.get_ref().0.is_empty());
+    writer.flush().unwrap();
+    assert_eq!(writer.get_ref().0, expected);
+
+    let mut writer = ByteWriter(Vec::new());
+    frame.encode_with_writer(&mut writer).unwrap();
+    assert_eq!(writer.0, expected);
+}
+
+#[test]
+fn v3_encode_vectored() {
+    let payload = vec![b'x'; 1024 * 1024];
+    let frame = Frame::Array {
+        data: mini_vec![
+            Frame::BulkString {
+                data: b"SET",
+                attributes: None,
+            },
+            Frame::BulkString {
+                data: b"key",
+                attributes: None,
+            },
+            Frame::BulkString {
+                data: &payload,
+                attributes: None,
+            },
+        ],
+        attributes: None,
+    };
+    let expected = frame.encode();
+
+    let mut encoder = VectoredEncoder::new();
+    V3::encode_vectored(frame, &mut encoder);
+    assert_eq!(encoder.len(), expected.len());
+
+    let segments = encoder.segments().collect::<Vec<_>>();
+    assert_eq!(segments.len(), 3);
+    assert_eq!(segments[0], b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1048576\r\n");
+    assert_eq!(segments[1].as_ptr(), payload.as_ptr());
+    assert_eq!(segments[2], b"\r\n");
+    assert_eq!(encoder.io_slices().len(), 3);
+
+    let mut out = Vec::new();
+    encoder.write_to(&mut out).unwrap();
+    assert_eq!(out, expected);
+
+    encoder.clear();
+    assert!(encoder.is_empty());
+}
+
+#[test]
+fn v3_encode_vectored_short_writes() {
+    let payload = vec![b'y'; 4096];
+    let frame = Frame::Map {
+        data: HashMap::from([(
+            Frame::BulkString {
+                data: b"field",
+                attributes: None,
+            },
+            Frame::BulkError {
+                data: &payload,
+                attributes: None,
+            },
+        )]),
+        attributes: Some(HashMap::from([(
+            Frame::SimpleString {
+                data: b"key-popularity",
+                attributes: None,
+            },
+            Frame::Boolean {
+                data: true,
+                attributes: None,
+            },
+        )])),
+    };
+    let expected = frame.encode();
+
+    let mut encoder = VectoredEncoder::with_payload_threshold(16);
+    frame.encode_vectored(&mut encoder);
+    let mut writer = ByteWriter(Vec::new());
+    encoder.write_to(&mut writer).unwrap();
+    assert_eq!(writer.0, expected);
+}
+
+#[test]
+fn v3_encode_to_buf() {
+    let frame = Frame::Push {
+        data: mini_vec![
+            Frame::Double {
+                data: -1.5,
+                raw: None,
+                attributes: None,
+            },
+            Frame::Boolean {
+                data: false,
+                attributes: Some(HashMap::from([(
+                    Frame::BigNumber {
+                        data: b"12345678901234567890",
+                        attributes: None,
+                    },
+                    Frame::Null {
+                        data: (),
+                        attributes: None,
+                    },
+                )])),
+            },
+            Frame::Set {
+                data: HashSet::from([Frame::Integer {
+                    data: -42,
+                    attributes: None,
+                }]),
+                attributes: None,
+            },
+            Frame::BulkError {
+                data: b"SYNTAX invalid",
+                attributes: None,
+            },
+        ],
+        attributes: None,
+    };
+
+    let mut buf = BytesMut::new();
+    V3::encode_to_buf(frame.clone(), &mut buf);
+    assert_eq!(
+        &buf[..],
+        b">4\r\n,-1.5\r\n|1\r\n(12345678901234567890\r\n_\r\n#f\r\n~1\r\n:-42\r\n!14\r\nSYNTAX invalid\r\n"
+    );
+
+    let mut writer = Vec::new();
+    frame.encode_with_writer(&mut writer).unwrap();
+    assert_eq!(&buf[..], writer);
+}
+
+fn random_double(rng: &mut ThreadRng) -> f64 {
+    match rng.random_range(0..4) {
+        0 => rng.random::<f64>() * 1e6,
+        1 => f64::from_bits(rng.random()),
+        2 => rng.random_range(-1000..1000) as f64,
+        _ => *[
+            0.0,
+            -0.0,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::NAN,
+            1e300,
+            1e-300,
+        ]
+        .get(rng.random_range(0..7))
+        .unwrap(),
+    }
+}
+
+fn random_text<'a>(rng: &mut ThreadRng, pool: &'a [u8]) -> &'a [u8] {
+    &pool[..rng.random_range(0..pool.len())]
+}
+
+fn random_attributes<'a>(
+    rng: &mut ThreadRng,
+    pool: &'a [u8],
+    depth: usize,
+) -> Option<HashMap<Frame<'a>, Frame<'a>>> {
+    if depth == 0 || !rng.random_bool(0.2) {
+        return None;
+    }
+    Some(
+        (0..rng.random_range(0..4))
+            .map(|_| (random_key(rng, pool), random_frame(rng, pool, depth - 1)))
+            .collect(),
+    )
+}
+
+/// Map keys and set members are limited to the types `Frame` can hash.
+fn random_key<'a>(rng: &mut ThreadRng, pool: &'a [u8]) -> Frame<'a> {
+    let data = random_text(rng, pool);
+    match rng.random_range(0..3) {
+        0 => Frame::BulkString {
+            data,
+            attributes: None,
+        },
+        1 => Frame::SimpleString {
+            data: &data[..data.len().min(64)],
+            attributes: None,
+        },
+        _ => Frame::Integer {
+            data: rng.random::<i64>() as isize,
+            attributes: None,
+        },
+    }
+}
+
+fn random_frame<'a>(rng: &mut ThreadRng, pool: &'a [u8], depth: usize) -> Frame<'a> {
+    let data = random_text(rng, pool);
+    let short = &data[..data.len().min(64)];
+    let attributes = random_attributes(rng, pool, depth);
+    let children = |rng: &mut ThreadRng| rng.random_range(0..8);
+    match rng.random_range(0..if depth == 0 { 10 } else { 15 }) {
+        0 => Frame::SimpleString {
+            data: short,
+            attributes,
+        },
+        1 => Frame::SimpleError {
+            data: short,
+            attributes,
+        },
+        2 => Frame::Boolean {
+            data: rng.random(),
+            attributes,
+        },
+        3 => Frame::Null {
+            data: (),
+            attributes,
+        },
+        4 => Frame::Integer {
+            data: rng.random::<i64>() as isize,
+            attributes,
+        },
+        5 => Frame::Double {
+            data: random_double(rng),
+            raw: None,
+            attributes,
+        },
+        6 => Frame::BulkString { data, attributes },
+        7 => Frame::BulkError { data, attributes },
+        8 => Frame::VerbatimString {
+            data: (*b"txt", data),
+            attributes,
+        },
+        9 => Frame::BigNumber {
+            data: b"-3492890328409238509324850943850943825024385",
+            attributes,
+        },
+        10 => Frame::Array {
+            data: (0..children(rng))
+                .map(|_| random_frame(rng, pool, depth - 1))
+                .collect(),
+            attributes,
+        },
+        11 => Frame::Map {
+            data: (0..children(rng))
+                .map(|_| (random_key(rng, pool), random_frame(rng, pool, depth - 1)))
+                .collect(),
+            attributes,
+        },
+        12 => Frame::Set {
+            data: (0..children(rng)).map(|_| random_key(rng, pool)).collect(),
+            attributes,
+        },
+        _ => Frame::Push {
+            data: (0..children(rng))
+                .map(|_| random_frame(rng, pool, depth - 1))
+                .collect(),
+            attributes,
+        },
+    }
+}
+
+#[test]
+fn v3_encode_len_matches_encode() {
+    let pool = vec![b'a'; 20_000];
+    let mut rng = rand::rng();
+    for _ in 0..2000 {
+        let frame = random_frame(&mut rng, &pool, 3);
+        assert_eq!(frame.encode_len(), frame.encode().len(), "{:?}", frame);
+    }
+}
+
+#[test]
+fn v3_double_round_trip() {
+    let mut rng = rand::rng();
+    for _ in 0..10_000 {
+        let data = random_double(&mut rng);
+        let frame = Frame::Double {
+            data,
+            raw: None,
+            attributes: None,
+        };
+        let encoded = frame.encode();
+        assert_eq!(frame.encode_len(), encoded.len());
+        let Ok(Some((Frame::Double { data: decoded, .. }, _))) = decode::<V3>(&encoded) else {
+            panic!("{:?} did not decode", String::from_utf8_lossy(&encoded));
+        };
+        if data.is_nan() {
+            assert!(decoded.is_nan());
+        } else {
+            assert_eq!(decoded.to_bits(), data.to_bits());
+        }
+    }
+
+    for (data, expected) in [
+        (f64::INFINITY, b",inf\r\n".as_slice()),
+        (f64::NEG_INFINITY, b",-inf\r\n"),
+        (f64::NAN, b",nan\r\n"),
+    ] {
+        let frame = Frame::Double {
+            data,
+            raw: None,
+            attributes: None,
+        };
+        assert_eq!(frame.encode(), expected);
+    }
+}
+
+#[test]
+fn v3_double_keeps_text() {
+    let input = b",1.50\r\n,1.23E-10\r\n,+3\r\n";
+    let frames = V3::parse_iter(input.as_slice())
+        .keep_double_text()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(
+        frames[1],
+        Frame::Double {
+            data: 1.23e-10,
+            raw: Some(b"1.23E-10"),
+            attributes: None,
+        }
+    );
+
+    let mut encoded = Vec::new();
+    for frame in &frames {
+        assert_eq!(frame.encode_len(), frame.encode().len());
+        encoded.extend(frame.encode());
+    }
+    assert_eq!(encoded, input);
+}
+
+/// Spells a double the ways a server might, with optional signs, leading
+/// zeros, fractions and exponents in either case.
+fn random_double_text(rng: &mut ThreadRng) -> String {
+    match rng.random_range(0..8) {
+        0 => return "inf".to_string(),
+        1 => return "-inf".to_string(),
+        _ => {}
+    }
+    let mut text = String::new();
+    match rng.random_range(0..3) {
+        0 => text.push('-'),
+        1 => text.push('+'),
+        _ => {}
+    }
+    for _ in 0..rng.random_range(1..6) {
+        text.push(char::from(b'0' + rng.random_range(0..10)));
+    }
+    if rng.random() {
+        text.push('.');
+        for _ in 0..rng.random_range(1..20) {
+            text.push(char::from(b'0' + rng.random_range(0..10)));
+        }
+    }
+    if rng.random() {
+        text.push(if rng.random() { 'e' } else { 'E' });
+        match rng.random_range(0..3) {
+            0 => text.push('-'),
+            1 => text.push('+'),
+            _ => {}
+        }
+        text.push_str(&rng.random_range(0..400).to_string());
+    }
+    text
+}
+
+#[test]
+fn v3_double_text_round_trip() {
+    let mut rng = rand::rng();
+    for _ in 0..10_000 {
+        let input = format!(",{}\r\n", random_double_text(&mut rng));
+        let frame = V3::parse_iter(input.as_bytes())
+            .keep_double_text()
+            .next()
+            .unwrap()
+            .unwrap();
+        let encoded = frame.encode();
+        assert_eq!(frame.encode_len(), encoded.len());
+        assert_eq!(encoded, input.as_bytes());
+
+        let Ok(Some((decoded, _))) = decode::<V3>(&encoded) else {
+            panic!("{:?} did not decode", input);
+        };
+        assert!(matches!(decoded, Frame::Double { raw: None, .. }));
+        assert_eq!(decoded, frame, "{:?}", input);
+    }
+}
+
+#[test]
+fn v3_streamed_string() {
+    let mut out = Vec::new();
+    let mut streamed = StreamedStringWriter::new(&mut out).unwrap();
+    streamed.write_all(b"Hell").unwrap();
+    streamed.write_all(b"").unwrap();
+    streamed.write_all(b"o world").unwrap();
+    streamed.finish().unwrap();
+    assert_eq!(out, b"$?\r\n;4\r\nHell\r\n;7\r\no world\r\n;0\r\n");
+
+    let payload = vec![b'x'; 20_000];
+    let mut out = Vec::new();
+    write_streamed_string(&mut out, &payload[..]).unwrap();
+    assert!(out.starts_with(b"$?\r\n;"));
+    assert!(out.ends_with(b"\r\n;0\r\n"));
+
+    let mut out = Vec::new();
+    write_streamed_string(&mut out, &b""[..]).unwrap();
+    assert_eq!(out, b"$?\r\n;0\r\n");
+}